@@ -0,0 +1,226 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # COBS framing for byte stream links
+//!
+//!   Consistent Overhead Byte Stuffing removes every zero from a
+//! report so zeros can delimit frames on a serial/UART link. Frames
+//! start and end with a delimiter, a receiver that joins mid frame
+//! drops the partial frame and syncs on the next one. Decoded frames
+//! must be exactly [RID_PACKET_SIZE] bytes.
+//!
+//!   A CRC-16 of the whole report ([crate::link::crc16()]) follows the
+//! report in each frame. Big reports can be damaged in flight and still
+//! decode to the right length, the check drops those frames too.
+//!
+//! ```
+//! use rid::{RID_PACKET_SIZE, cobs::{encode, CobsDecoder, COBS_FRAME_SIZE}};
+//!
+//! let report = [7u8; RID_PACKET_SIZE];
+//! let mut frame = [0u8; COBS_FRAME_SIZE];
+//! let length = encode(&report, &mut frame);
+//!
+//! let mut decoder = CobsDecoder::default();
+//! let decoded = frame[..length].iter().find_map(|&byte| decoder.push(byte));
+//!
+//! assert_eq!(decoded, Some(report));
+//! ```
+
+use crate::{RIDReport, RID_PACKET_SIZE, link::crc16};
+
+/// Frame delimiter
+pub const COBS_DELIMITER: u8 = 0;
+/// Bytes of the frame check after the report
+pub const COBS_CHECK_BYTES: usize = 2;
+/// Bytes encoded in each frame, a report and its frame check
+pub const COBS_PAYLOAD_SIZE: usize = RID_PACKET_SIZE + COBS_CHECK_BYTES;
+/// Largest encoded report, including both delimiters
+pub const COBS_FRAME_SIZE: usize = COBS_PAYLOAD_SIZE + (COBS_PAYLOAD_SIZE / 254) + 3;
+
+/// alias for readability
+pub type CobsFrame = [u8; COBS_FRAME_SIZE];
+
+/// Encode a report and its frame check into a frame.
+///
+/// Returns the length of the frame including the delimiters.
+pub fn encode(report: &RIDReport, frame: &mut CobsFrame) -> usize {
+
+    frame[0] = COBS_DELIMITER;
+
+    let check = crc16(report).to_be_bytes();
+
+    let mut code_index = 1;
+    let mut write_index = 2;
+    let mut code = 1u8;
+
+    for &byte in report.iter().chain(check.iter()) {
+
+        if byte == COBS_DELIMITER {
+
+            frame[code_index] = code;
+            code_index = write_index;
+            write_index += 1;
+            code = 1;
+
+        }
+        else {
+
+            frame[write_index] = byte;
+            write_index += 1;
+            code += 1;
+
+            if code == 0xFF {
+
+                frame[code_index] = code;
+                code_index = write_index;
+                write_index += 1;
+                code = 1;
+
+            }
+        }
+    }
+
+    frame[code_index] = code;
+    frame[write_index] = COBS_DELIMITER;
+
+    write_index + 1
+
+}
+
+/// Decode the bytes between two delimiters into a report.
+///
+/// Returns false if the bytes are not valid COBS, do not 
+/// decode to exactly [RID_PACKET_SIZE] bytes and a frame check
+/// or the report does not match its frame check.
+pub fn decode(encoded: &[u8], report: &mut RIDReport) -> bool {
+
+    let mut decoded = [0u8; COBS_PAYLOAD_SIZE];
+    let mut read_index = 0;
+    let mut write_index = 0;
+
+    while read_index < encoded.len() {
+
+        let code = encoded[read_index];
+        read_index += 1;
+
+        if code == COBS_DELIMITER || read_index + code as usize - 1 > encoded.len() || write_index + code as usize - 1 > COBS_PAYLOAD_SIZE {
+            return false;
+        }
+
+        for &byte in &encoded[read_index..read_index + code as usize - 1] {
+            decoded[write_index] = byte;
+            write_index += 1;
+        }
+
+        read_index += code as usize - 1;
+
+        // Every group except the last and full groups ends in a zero
+        if code != 0xFF && read_index < encoded.len() {
+
+            if write_index >= COBS_PAYLOAD_SIZE {
+                return false;
+            }
+
+            decoded[write_index] = 0;
+            write_index += 1;
+
+        }
+    }
+
+    let check = u16::from_be_bytes([decoded[RID_PACKET_SIZE], decoded[RID_PACKET_SIZE + 1]]);
+
+    match write_index == COBS_PAYLOAD_SIZE && check == crc16(&decoded[..RID_PACKET_SIZE]) {
+        true => {
+
+            report.copy_from_slice(&decoded[..RID_PACKET_SIZE]);
+            true
+
+        },
+        false => false,
+    }
+
+}
+
+/// Collects bytes from a stream and produces reports.
+pub struct CobsDecoder {
+    /// Bytes since the last delimiter
+    buffer: CobsFrame,
+    /// Number of bytes in the buffer
+    length: usize,
+    /// The current frame is too long and is being skipped
+    overflow: bool,
+    /// Number of frames that were dropped
+    pub dropped: usize,
+}
+
+impl Default for CobsDecoder {
+    fn default() -> CobsDecoder {
+        CobsDecoder {
+            buffer: [0u8; COBS_FRAME_SIZE],
+            length: 0,
+            overflow: false,
+            dropped: 0,
+        }
+    }
+}
+
+impl CobsDecoder {
+
+    /// Consume one byte from the stream.
+    ///
+    /// Returns a report when the byte completes a valid frame.
+    /// Empty frames are ignored, partial and corrupted frames
+    /// are counted and dropped.
+    pub fn push(&mut self, byte: u8) -> Option<RIDReport> {
+
+        if byte != COBS_DELIMITER {
+
+            match self.length < COBS_FRAME_SIZE {
+                true => {
+
+                    self.buffer[self.length] = byte;
+                    self.length += 1;
+
+                },
+                false => self.overflow = true,
+            }
+
+            return None;
+
+        }
+
+        let length = self.length;
+        let overflow = self.overflow;
+
+        self.length = 0;
+        self.overflow = false;
+
+        if length == 0 {
+            return None;
+        }
+
+        let mut report = [0u8; RID_PACKET_SIZE];
+
+        match !overflow && decode(&self.buffer[..length], &mut report) {
+            true => Some(report),
+            false => {
+
+                self.dropped += 1;
+                None
+
+            },
+        }
+
+    }
+}
@@ -0,0 +1,104 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Host errors
+//!
+//!   Everything the host side can fail at is a [RidError], the library
+//! doesn't panic or print on a bad link. [RidError::Timeout] is the usual
+//! "no reply this cycle", the rest are for supervisors to act on.
+//!
+//! ```
+//! use rid::host::{error::RidError, layer::RIDLayer, transport::loopback::LoopbackTransport};
+//!
+//! let (host, _client) = LoopbackTransport::pair();
+//! let mut layer = RIDLayer::from_transport(host);
+//!
+//! assert_eq!(layer.spin(), Err(RidError::Timeout));
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    io::{self, ErrorKind},
+};
+
+use hidapi::HidError;
+
+use crate::RID_PACKET_SIZE;
+
+/// Errors from the host side of a link
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RidError {
+    /// No device with the vid, pid (or serial number) is connected
+    NotFound,
+    /// The device is connected but could not be opened, check the udev rules
+    PermissionDenied,
+    /// The device or the other end of the link went away
+    Disconnected,
+    /// Only this many bytes of a report were written
+    ShortWrite(usize),
+    /// No report arrived in time
+    Timeout,
+    /// Anything else the OS or hidapi reported
+    Other(String),
+}
+
+impl fmt::Display for RidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        match self {
+            RidError::NotFound => write!(f, "device not found"),
+            RidError::PermissionDenied => write!(f, "permission denied, check the udev rules"),
+            RidError::Disconnected => write!(f, "device disconnected"),
+            RidError::ShortWrite(n) => write!(f, "short write, {n} of {RID_PACKET_SIZE} bytes"),
+            RidError::Timeout => write!(f, "timed out"),
+            RidError::Other(message) => write!(f, "{message}"),
+        }
+
+    }
+}
+
+impl Error for RidError {}
+
+impl From<io::Error> for RidError {
+    fn from(e: io::Error) -> RidError {
+
+        match e.kind() {
+            ErrorKind::NotFound => RidError::NotFound,
+            ErrorKind::PermissionDenied => RidError::PermissionDenied,
+            ErrorKind::WouldBlock | ErrorKind::TimedOut => RidError::Timeout,
+            ErrorKind::WriteZero => RidError::ShortWrite(0),
+            ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::ConnectionRefused
+            | ErrorKind::NotConnected
+            | ErrorKind::UnexpectedEof => RidError::Disconnected,
+            _ => RidError::Other(e.to_string()),
+        }
+
+    }
+}
+
+impl From<HidError> for RidError {
+    fn from(e: HidError) -> RidError {
+
+        match e {
+            HidError::IoError { error } => error.into(),
+            HidError::IncompleteSendError { sent, .. } => RidError::ShortWrite(sent),
+            e => RidError::Other(e.to_string()),
+        }
+
+    }
+}
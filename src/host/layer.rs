@@ -12,68 +12,86 @@
  ********************************************************************************/
 //!
 //!
-//! # HidApi wrapper for RID comms
-
+//! # RID comms layer
+//!
+//!   Host side PTP stamping and timing on top of any [RidTransport].
+//!
+//!   A link that fails with [RidError::Disconnected] is reconnected by
+//! [RIDLayer::transfer()], the client restarted so its clock and the PTP
+//! fit start over ([RIDLayer::reset_link()]).
 
-extern crate hidapi;
-use hidapi::{HidApi, HidDevice};
 
 // use chrono::{DateTime, Utc};
-use std::time::Instant;
+use std::time::{self, Instant};
 
 use crate::{
     RIDReport, 
-    RID_PACKET_SIZE, RID_CYCLE_TIME_US,
-    RID_TASK_INDEX, RID_MODE_INDEX,
-    ptp::{Duration, TimeStamp, USEC_PER_SEC, SEC_PER_HOUR}
+    RID_CYCLE_TIME_US,
+    link::{self, LinkStats},
+    ptp::{Duration, Micros, TimeStamp, ptp_offset, round, estimator::SkewEstimator, filter::DelayFilter, servo::ClockServo, stats::{PtpStats, PtpSnapshot}, sync::{SyncMonitor, SyncState}, USEC_PER_HOUR},
+    rtnt::packet::RidPacket,
+    host::{error::RidError, scheduler::CycleScheduler, transport::{RidTransport, hid::HidTransport}},
 };
 
-/// Microsecond to Hour constant: microseconds = hours * USEC_PER_HOUR
-pub const USEC_PER_HOUR: f32 = USEC_PER_SEC as f32 * SEC_PER_HOUR as f32;
-
-/// helper function to create a new HidDevice
-/// not really relevant since monothread
-pub fn new_device(vid: u16, pid: u16, hidapi: &mut HidApi) -> HidDevice {
-        
-    let device = hidapi.open(vid, pid).expect("[HID-Layer] Failed to open device");
-            
-    device.set_blocking_mode(false).unwrap();
-
-    device
-}
-
 
 /// the host side interface to the microcontroller
 /// this should provide an abstraction for the
 /// task deploy system to utilize.
-pub struct RIDLayer {
-    /// USB device vid
-    pub vid: u16,
-    /// USB device pid
-    pub pid: u16,
-
-    /// Hours that have elapsed on the host
-    pub host_hours: f32,
-    /// Microseconds the host started at
-    pub host_start: f32,
-    /// Hours that have elapsed on the client
-    pub client_hours: f32,
-    /// Microseconds the client started at
-    pub client_start: f32,
-
-    /// The linear offset coefficients
-    pub linear_offset: [f32; 2],
-
-    /// [Instant] to track change in time
+pub struct RIDLayer<T: RidTransport = HidTransport> {
+    /// Host time the layer started at
+    pub host_start: Micros,
+    /// Client time of the first reply, None until the client replies
+    pub client_start: Option<Micros>,
+    /// Counts the rollovers of the client's write stamps
+    pub client_clock: Duration,
+
+    /// Full host time the client's last reply answered (t1)
+    pub host_write: Micros,
+    /// Full client time of the client's last read (t2)
+    pub client_read: Micros,
+    /// Full client time of the client's last write (t3)
+    pub client_write: Micros,
+    /// Full host time of the last read (t4)
+    pub host_read: Micros,
+
+    /// Drops exchanges with a long round trip, they are likely asymmetric
+    pub delay_filter: DelayFilter,
+    /// Smoothed offset and frequency ratio of the client clock
+    pub servo: ClockServo,
+    /// Least squares fit of the client clock, C(t) = m * H(t) + b
+    pub estimator: SkewEstimator,
+    /// Whether the fit can be trusted, updated every [RIDLayer::transfer()]
+    pub sync: SyncMonitor,
+    /// Statistics of every [RIDLayer::transfer()], see [RIDLayer::snapshot()]
+    pub stats: PtpStats,
+
+    /// [Instant] the layer started at, host time counts from it
     pub timer: Instant,
-
-    /// USB hidapi (C wrapper lib)
-    pub hidapi: HidApi,
-    /// USB HidDevice class
-    pub device: HidDevice,
-
-    /// ['Duration'] keeps track of host "system_time", H(t)
-    pub system_time: Duration,
+    /// Paces [RIDLayer::timestep()]
+    pub scheduler: CycleScheduler,
+
+    /// The link reports are sent and received on
+    pub transport: T,
+    /// How long [RIDLayer::read] waits for a report
+    pub read_timeout: time::Duration,
+    /// False after a [RidError::Disconnected] until the transport reconnects
+    pub connected: bool,
+    /// Time between reconnect attempts
+    pub reconnect_period: time::Duration,
+    /// Number of times the link came back
+    pub reconnects: usize,
+    /// The link came back, see [RIDLayer::reconnected()]
+    reconnected: bool,
+    /// Last reconnect attempt
+    last_attempt: Option<Instant>,
+
+    /// Sequence number of the next report written
+    pub sequence: u16,
+    /// Loss statistics of reports from the client
+    pub link_stats: LinkStats,
+
+    /// Host "system_time" at the last timestep, H(t)
+    pub system_time: Micros,
     /// ['TimeStamp'] for synchronization
     pub ptp_stamp: TimeStamp,
 
@@ -81,45 +99,60 @@ pub struct RIDLayer {
 
 impl RIDLayer {
     /// Create a new RID layer
-    /// Connects to the device with the specified vid, pid
-    /// panics if the device cant be found. If it does not 
-    /// have permission check your udev rules and make sure
-    /// it includes the vid pid.
-    pub fn new(vid: u16, pid: u16) -> RIDLayer {
+    /// Connects to the device with the specified vid, pid,
+    /// see [HidTransport::new()] for the errors.
+    pub fn new(vid: u16, pid: u16) -> Result<RIDLayer, RidError> {
 
-        let mut hidapi = HidApi::new().expect("Failed to create API instance");
-        let device = new_device(vid, pid, &mut hidapi);
+        Ok(RIDLayer::from_transport(HidTransport::new(vid, pid)?))
 
-        let system_time = Duration::new(USEC_PER_HOUR as u32 - 5_000_000);
-        let ptp_stamp = TimeStamp::new(0, 0, 0, 0);
-
-        let timer = Instant::now();
+    }
+}
 
-        let host_hours = 0.0;
-        let host_start = system_time.micros() as f32;
+impl<T: RidTransport> RIDLayer<T> {
+    /// Create a new RID layer on top of a transport.
+    ///
+    /// Reads do not block, set [RIDLayer::read_timeout]
+    /// to wait for replies.
+    pub fn from_transport(transport: T) -> RIDLayer<T> {
 
-        let client_hours = 0.0;
-        let client_start = 0.0;
+        // Start near the end of the hour so every run crosses a rollover
+        let system_time = Micros::new(USEC_PER_HOUR - 5_000_000);
+        let ptp_stamp = TimeStamp::new(0, 0, 0, 0);
 
-        let linear_offset = [0.0, 0.0];
+        let timer = Instant::now();
 
+        let read_timeout = time::Duration::ZERO;
+        let reconnect_period = time::Duration::from_millis(250);
 
         RIDLayer {
-            vid,
-            pid,
-
-            host_hours,
-            host_start,
+            host_start: system_time,
+            client_start: None,
+            client_clock: Duration::default(),
 
-            client_hours,
-            client_start,
+            host_write: Micros::ZERO,
+            client_read: Micros::ZERO,
+            client_write: Micros::ZERO,
+            host_read: Micros::ZERO,
 
-            linear_offset,
+            delay_filter: DelayFilter::default(),
+            servo: ClockServo::default(),
+            estimator: SkewEstimator::default(),
+            sync: SyncMonitor::default(),
+            stats: PtpStats::default(),
 
             timer,
+            scheduler: CycleScheduler::default(),
 
-            hidapi,
-            device,
+            transport,
+            read_timeout,
+            connected: true,
+            reconnect_period,
+            reconnects: 0,
+            reconnected: false,
+            last_attempt: None,
+
+            sequence: 0,
+            link_stats: LinkStats::default(),
 
             system_time,
             ptp_stamp,
@@ -127,258 +160,369 @@ impl RIDLayer {
         }
     }
 
-    /// try reading a Report into a buffer
-    pub fn read(&mut self, buffer: &mut RIDReport) -> usize {
+    /// The current host time
+    pub fn host_now(&self) -> Micros {
+
+        self.host_start + self.timer.elapsed().as_micros() as i64
+
+    }
+
+    /// try reading a Report into a buffer, tracks the
+    /// clients sequence numbers in [RIDLayer::link_stats].
+    /// [RidError::Timeout] if nothing arrived in [RIDLayer::read_timeout].
+    pub fn read(&mut self, buffer: &mut RIDReport) -> Result<(), RidError> {
         
-        match self.device.read(buffer) {
-            Ok(val) => {
+        self.transport.recv(buffer, self.read_timeout)?;
 
-                self.ptp_stamp.host_read(buffer, self.system_time.micros() + self.timer.elapsed().as_micros() as u32);
+        let now = self.host_now();
 
-                val
+        self.ptp_stamp.host_read(buffer, now.wrapped());
+        self.link_stats.track(link::read_sequence(buffer));
+        self.unwrap_stamps(buffer, now);
 
-            },
-            _ => {
+        Ok(())
+
+    }
+
+    /// Recover the full event times from the stamps of a reply.
+    ///
+    /// Host stamps are unwrapped around the read time, client writes
+    /// advance [RIDLayer::client_clock] (they never jump by half an hour).
+    fn unwrap_stamps(&mut self, buffer: &RIDReport, now: Micros) {
+
+        let (_, host_write) = self.ptp_stamp.read_host_stamp(buffer);
+
+        self.host_read = now;
+        self.host_write = now.unwrap(host_write);
+        self.client_write = self.client_clock.advance(self.ptp_stamp[1]);
+        self.client_read = self.client_write.unwrap(self.ptp_stamp[0]);
+
+        if self.client_start.is_none() {
+
+            self.client_start = Some(self.client_write);
 
-                println!("[HID-Layer] Failed to read");
-                
-                0
-            
-            },
         }
 
     }
 
-    /// try writing a Report from a buffer
-    pub fn write(&mut self, buffer: &mut RIDReport) {
+    /// try writing a Report from a buffer, numbers the report
+    pub fn write(&mut self, buffer: &mut RIDReport) -> Result<(), RidError> {
         
-        self.ptp_stamp.host_stamp(buffer, self.system_time.micros() + self.timer.elapsed().as_micros() as u32);
+        link::write_sequence(buffer, self.sequence);
+        self.sequence = self.sequence.wrapping_add(1);
 
-        match self.device.write(buffer) {
-            Ok(RID_PACKET_SIZE) => {},
-            _ => println!("[HID-Layer] Failed to write"),
-        }
+        self.ptp_stamp.host_stamp(buffer, self.host_now().wrapped());
+
+        self.transport.send(buffer)
 
     }
 
-    /// Calculate the current ptp offset including hour offsets.
+    /// Calculate the ptp offset of the last exchange (microseconds).
     ///
-    /// Uses the [TimeStamp] offset calculation and adds the difference in
-    /// client and host hours.
+    /// Uses the full event times, so the hourly rollover of
+    /// the stamps does not show up in the offset. This is a single noisy
+    /// sample, [RIDLayer::servo] has the smoothed offset.
     ///
-    /// C(t) = H(t) + o(t) // microseconds
+    /// C(t) = H(t) + o(t)
     ///
     /// o(t) = (t2 + t3 - t1 - t4) / 2
-    ///
-    /// C'(t) = H'(t) + o'(t) // hours
-    /// 
-    /// o'(t) = (t2' + t3' - t1' - t4') / 2
-    ///
-    /// client hours = t2' = t3' // comms are fast enough this should hold
-    ///
-    /// host hours = t1' = t4'
-    ///
-    /// o'(t) = (client hours - host hours)
-    ///
-    /// C'(t) + C(t) = H(t) + H'(t) + (o(t) + o'(t))
-    ///
-    /// This function returns (o(t) + o'(t)), the total hours and micros offset.
-    pub fn ptp_offset(&self) -> f32 {
+    pub fn ptp_offset(&self) -> i64 {
 
-        self.ptp_stamp.offset() + ((self.client_hours - self.host_hours) * USEC_PER_HOUR)
+        ptp_offset(self.host_write.micros() as i64,
+            self.client_read.micros() as i64,
+            self.client_write.micros() as i64,
+            self.host_read.micros() as i64,
+        )
     
     }
 
-    /// Calculate the time that has elapsed on the host.
-    /// This utilizes the [Duration], [Instant] and recorded host_start.
-    ///
-    /// This includes hourly rollovers.
-    pub fn host_elapsed(&self) -> f32 {
+    /// Calculate the time that has elapsed on the host (microseconds).
+    /// This utilizes the system time, [Instant] and recorded host_start.
+    pub fn host_elapsed(&self) -> i64 {
 
-        (self.system_time.micros() + self.timer.elapsed().as_micros() as u32) as f32 
-            + (self.host_hours * USEC_PER_HOUR) 
-            - self.host_start
+        self.host_now() - self.host_start
 
     }
 
-    /// Calculate the time that has elapsed on the client.
-    /// This utilizes the latest client write time from
-    /// the [TimeStamp] and recorded start time.
-    ///
-    /// This also includes hourly rollovers. 
-    pub fn client_elapsed(&self) -> f32 {
+    /// Calculate the time that has elapsed on the client (microseconds).
+    /// This utilizes the latest client write time and the first
+    /// one, 0 until the client replies.
+    pub fn client_elapsed(&self) -> i64 {
 
-        self.ptp_stamp[1] as f32 + (self.client_hours * USEC_PER_HOUR) - self.client_start
+        match self.client_start {
+            Some(start) => self.client_write - start,
+            None => 0,
+        }
 
     }
 
-    /// Apply the ptp offset to a host time.
+    /// Apply the servo's offset to a host time.
     /// 
-    /// C'(t) + C(t) = H(t) + H'(t) + (o(t) + o'(t))
+    /// C(t) = H(t) + o(t)
     /// 
     /// Inputs
-    /// - t: f32, H(t) + H'(t) the full host time
+    /// - t: [Micros], H(t) the full host time
     ///
     /// Returns
-    /// - C(t) + C'(t): f32
-    pub fn ptp_to_client(&self, t: f32) -> f32 {
+    /// - C(t): [Micros] the full client time
+    pub fn ptp_to_client(&self, t: Micros) -> Micros {
 
-        t + self.ptp_offset() 
+        self.servo.to_client(t)
 
     }
 
-    /// Apply the linear offset to a host time.
+    /// Apply the [SkewEstimator]'s fit to a host time.
     /// 
     /// C(t) = m * H(t) + b
     /// 
     /// Inputs
-    /// - t: f32, H(t) elapsed host time
+    /// - t: i64, H(t) elapsed host time (microseconds)
     ///
     /// Returns
-    /// - C(t): f32, elapsed client time
-    pub fn linear_to_client(&self, t: f32) -> f32 {
-        
+    /// - C(t): i64, elapsed client time (microseconds), t until the client replies
+    pub fn linear_to_client(&self, t: i64) -> i64 {
 
-        (self.linear_offset[0] * (t + self.host_start)) + self.linear_offset[1] - self.client_start
+        match self.client_start {
+            Some(client_start) => self.estimator.fit().to_client(self.host_start + t) - client_start,
+            None => t,
+        }
 
     }
 
-    /// Apply the ptp offset to a client time.
+    /// Apply the servo's offset to a client time.
     /// 
-    /// H(t) + H'(t) = C'(t) + C(t) + (o(t) + o'(t))
+    /// H(t) = C(t) - o(t)
     /// 
     /// Inputs
-    /// - t: f32, C(t) + C'(t) the full client time
+    /// - t: [Micros], C(t) the full client time
     ///
     /// Returns
-    /// - H(t) + H'(t): f32 the full host time
-    pub fn ptp_to_host(&self, t: f32) -> f32 {
+    /// - H(t): [Micros] the full host time
+    pub fn ptp_to_host(&self, t: Micros) -> Micros {
 
-        t - self.ptp_offset()
+        self.servo.to_host(t)
 
     }
 
-    /// Apply the linear offset to a client time.
+    /// Apply the [SkewEstimator]'s fit to a client time.
     /// 
     /// H(t) = (C(t) - b) / m
     /// 
     /// Inputs
-    /// - t: f32, C(t) elapsed client time
+    /// - t: i64, C(t) elapsed client time (microseconds)
     ///
     /// Returns
-    /// - H(t) elapsed host time
+    /// - H(t): i64, elapsed host time (microseconds), t until the client replies
     ///
-    pub fn linear_to_host(&self, t: f32) -> f32 {
+    pub fn linear_to_host(&self, t: i64) -> i64 {
         
-        (t - self.linear_offset[1]) / self.linear_offset[0]
+        match self.client_start {
+            Some(client_start) => self.estimator.fit().to_host(client_start + t) - self.host_start,
+            None => t,
+        }
 
     }
 
-    /// Delay helper, makes loops readable
-    pub fn delay(&mut self) -> u32 {
+    /// How far from a host time a trigger may run (microseconds).
+    ///
+    /// The client runs triggers on the first cycle its estimate of the
+    /// host time passes the trigger, so the bound is 3 standard deviations
+    /// of the fit at that time plus a cycle. Saturates until the fit
+    /// has enough points.
+    pub fn trigger_bound(&self, at: Micros) -> i64 {
 
-        let mut t = self.timer.elapsed().as_micros();
+        round(3.0 * self.estimator.fit().uncertainty(at)).saturating_add(RID_CYCLE_TIME_US as i64)
 
-        while t < RID_CYCLE_TIME_US as u128 {
+    }
 
-            t = self.timer.elapsed().as_micros();
-        
-        }
+    /// Wait for the end of the cycle ([RIDLayer::scheduler]), makes loops real nice.
+    ///
+    /// Returns the host time the wait ended at, also kept in [RIDLayer::system_time]
+    pub fn timestep(&mut self) -> Micros {
 
-        self.timer = Instant::now();
-        
-        t as u32
+        self.scheduler.wait();
 
-    }
+        self.system_time = self.host_now();
 
-    /// another delay helper, makes loops real nice
-    pub fn timestep(&mut self) -> u32 {
+        self.system_time
 
-        let t = self.delay();
+    }
 
 
-        if self.system_time.micros() + t > USEC_PER_HOUR as u32 {
+    /// Write a report, try to read the reply and update the ptp stamp.
+    ///
+    /// Every reply that passes the [DelayFilter] is a sample
+    /// for the [ClockServo] and the [SkewEstimator]. Every transfer
+    /// updates the [SyncMonitor] and [RIDLayer::stats], replied or not.
+    ///
+    /// A disconnected link is reconnected first ([RIDLayer::reconnect()]).
+    ///
+    /// The reply is left in the buffer. Returns the flight time
+    /// (microseconds, 0 for the first reply), [RidError::Timeout]
+    /// if the client did not reply.
+    pub fn transfer(&mut self, buffer: &mut RIDReport) -> Result<i64, RidError> {
 
-            self.host_hours += 1.0;
-        
-        }
+        let reply = self.reconnect().and_then(|_| {
 
-        self.system_time.add_micros(t)
+            let first_reply = self.client_start.is_none();
 
-    }
+            self.write(buffer)?;
+            self.read(buffer)?;
+
+            Ok(first_reply)
 
+        });
 
-    /// Write, try to read and update the ptp stamp and system time
-    pub fn spin(&mut self) -> f32 {
+        let flight_time = match reply {
 
-        let mut buffer = [0; RID_PACKET_SIZE];
-        buffer[RID_TASK_INDEX] = 255;
-        buffer[RID_MODE_INDEX] = 255;
+            Ok(first_reply) => {
 
-        self.write(&mut buffer);
+                if self.delay_filter.check(self.host_write, self.client_read, self.client_write, self.host_read) {
 
-        let prev_client_read = self.ptp_stamp[0];
+                    self.servo.sample(self.host_write, self.client_read, self.client_write, self.host_read);
+                    self.estimator.sample(self.host_write, self.client_read, self.client_write, self.host_read);
 
-        match self.read(&mut buffer) {
+                }
 
-            RID_PACKET_SIZE => {
+                let flight_time = match first_reply {
+                    true => None,
+                    false => Some(self.host_read - self.host_write),
+                };
 
+                self.stats.exchange(self.ptp_offset(), flight_time, self.conversion_error());
 
-                if self.client_start == 0.0 {
+                Ok(flight_time.unwrap_or(0))
 
-                    self.client_start = self.ptp_stamp[1] as f32;
+            }
+            Err(e) => {
 
-                    return 0.0;
-                
+                if e == RidError::Disconnected {
+                    self.connected = false;
                 }
-                else {
 
-                    // Handles hour counts wrapping
-                    if self.ptp_stamp[1] < prev_client_read {
+                self.stats.missed();
+                Err(e)
+
+            },
+        };
+
+        self.sync.update(self.host_now(), &self.estimator.fit(), flight_time.is_ok());
 
-                        self.client_hours += 1.0;
-                    }
+        flight_time
 
-                    let (_, hw) = self.ptp_stamp.read_host_stamp(&buffer);
+    }
 
-                    // Updates the linear offset coefficients
-                    self.linear_offset[0] = self.client_elapsed() / self.host_elapsed();
-                    self.linear_offset[1] = self.client_start - (self.linear_offset[0] * self.host_start);
+    /// Try reconnecting a disconnected link, at most once per
+    /// [RIDLayer::reconnect_period]. A link that comes back
+    /// starts over with [RIDLayer::reset_link()].
+    ///
+    /// Returns Ok if the link is up, [RidError::Disconnected] between
+    /// attempts or the error of the last attempt.
+    pub fn reconnect(&mut self) -> Result<(), RidError> {
 
-                    return self.ptp_stamp[2] as f32 - hw as f32;
+        if self.connected {
+            return Ok(());
+        }
 
-                }
+        let now = Instant::now();
 
-            }
-            _ => 0.0,
+        if self.last_attempt.is_some_and(|last| now - last < self.reconnect_period) {
+            return Err(RidError::Disconnected);
         }
 
+        self.last_attempt = Some(now);
+
+        self.transport.reconnect()?;
+
+        self.reset_link();
+
+        self.connected = true;
+        self.reconnected = true;
+        self.reconnects += 1;
+
+        Ok(())
+
+    }
+
+    /// Forget everything about the client: its clock, the PTP fit,
+    /// sync state and link statistics. The host clock and
+    /// [RIDLayer::stats] keep going.
+    pub fn reset_link(&mut self) {
+
+        self.client_start = None;
+        self.client_clock = Duration::default();
+
+        self.host_write = Micros::ZERO;
+        self.client_read = Micros::ZERO;
+        self.client_write = Micros::ZERO;
+        self.host_read = Micros::ZERO;
+
+        self.delay_filter.reset();
+        self.servo.reset();
+        self.estimator.reset();
+        self.sync.reset();
+
+        self.link_stats.reset();
+        self.ptp_stamp = TimeStamp::new(0, 0, 0, 0);
+
     }
 
-    /// Only used in the performance tests
-    pub fn print_header(&self) {
-        println!("\n[PTP-DEMO]\tC(t) = {:.3} * H(t) + {:.3}", self.linear_offset[0], self.linear_offset[1]);
-        println!("Host (s)\t\tClient (s)\t\tConversion Error <host, client> (us)");
+    /// True once after the link came back. The client lost its
+    /// tasks, the host [crate::rtnt::task_manager::TaskManager] should
+    /// push them again ([crate::rtnt::task_manager::TaskManager::reconnect()]).
+    pub fn reconnected(&mut self) -> bool {
+
+        std::mem::take(&mut self.reconnected)
+
     }
 
-    /// Only used in the performance tests, returns estimate errors
-    pub fn print(&self) -> (f32, f32) {
+    /// The [SyncState] after the last transfer, gate
+    /// time critical commands on [SyncState::Locked]
+    pub fn sync_state(&self) -> SyncState {
+
+        self.sync.state()
 
-        let host_time = self.system_time.micros() as f32 + (self.host_hours * USEC_PER_HOUR);
-        let client_time = self.ptp_stamp[1] as f32 + (self.client_hours * USEC_PER_HOUR);
+    }
 
-        let host_err = host_time - self.ptp_to_host(client_time);
-        let client_err = client_time - self.ptp_to_client(host_time);
+    /// Transfer a PTP probe, see [RIDLayer::transfer()]
+    pub fn spin(&mut self) -> Result<i64, RidError> {
 
-        println!("  {:.4}\t\t{:.4}\t\t{:.0}\t{:.0}", 
-            self.system_time.micros() as f32  / 1_000_000.0,
-            client_time / 1_000_000.0,
-            host_err,
-            client_err,
-        );
+        self.transfer(&mut RidPacket::Ptp.encode())
 
-        (host_err, client_err)
     }
-}
 
+    /// Errors of the servo's conversions between the last host read and client write
+    /// (microseconds), <host read - H(client write), client write - C(host read)>
+    pub fn conversion_error(&self) -> (i64, i64) {
+
+        (self.host_read - self.ptp_to_host(self.client_write),
+            self.client_write - self.ptp_to_client(self.host_read))
 
+    }
+
+    /// The current fit, sync state and statistics of every exchange
+    pub fn snapshot(&self) -> PtpSnapshot {
+
+        let now = self.host_now();
+        let fit = self.estimator.fit();
+
+        PtpSnapshot {
+            host_time: now.micros(),
+            host_elapsed: self.host_elapsed(),
+            client_elapsed: self.client_elapsed(),
+            sync: self.sync.state(),
+            loss: self.sync.loss(),
+            skew: fit.skew(),
+            skew_std: Some(fit.drift_std).filter(|std| std.is_finite()),
+            intercept: self.linear_to_client(0),
+            uncertainty: Some(fit.uncertainty(now)).filter(|std| std.is_finite()),
+            points: fit.points,
+            servo_ratio: self.servo.ratio(),
+            accepted: self.delay_filter.accepted(),
+            rejected: self.delay_filter.rejected(),
+            lost: self.link_stats.lost,
+            stats: self.stats,
+        }
+
+    }
+}
@@ -0,0 +1,165 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Multi-device host
+//!
+//!   Rigs can have several clients with the same vid, pid. A [DeviceManager]
+//! opens each of them as a [RIDDevice], a named [HostSession] with its own
+//! PTP state and host tasks, and spins them all once per cycle.
+//!
+//! ```
+//! use rid::host::{manager::DeviceManager, sim::{SimClient, SimConfig}, transport::loopback::LoopbackTransport};
+//!
+//! let mut manager = DeviceManager::default();
+//!
+//! let (host, client) = LoopbackTransport::pair();
+//! let _sim = SimClient::new(client, SimConfig::default()).spawn();
+//!
+//! let index = manager.add("sim-0", host);
+//! manager.devices[index].session.layer.read_timeout = std::time::Duration::from_millis(10);
+//! manager.spin();
+//! ```
+
+use hidapi::HidApi;
+
+use crate::{
+    host::{
+        error::RidError,
+        scheduler::CycleScheduler,
+        session::HostSession,
+        transport::{RidTransport, hid::{self, HidTransport}},
+    },
+};
+
+/// A client opened by a [DeviceManager]
+pub struct RIDDevice<T: RidTransport = HidTransport> {
+    /// Name of the device, the serial number for HID devices
+    pub name: String,
+    /// Link, PTP state and host side tasks of the device
+    pub session: HostSession<T>,
+}
+
+impl<T: RidTransport> RIDDevice<T> {
+    /// Create a device with no tasks
+    pub fn new(name: &str, transport: T) -> RIDDevice<T> {
+
+        RIDDevice {
+            name: name.to_string(),
+            session: HostSession::new(transport),
+        }
+
+    }
+}
+
+/// Owns and spins several [RIDDevice]s
+pub struct DeviceManager<T: RidTransport = HidTransport> {
+    /// Opened devices
+    pub devices: Vec<RIDDevice<T>>,
+    /// Paces [DeviceManager::timestep()] for every device
+    pub scheduler: CycleScheduler,
+}
+
+impl<T: RidTransport> Default for DeviceManager<T> {
+    /// A manager without devices
+    fn default() -> DeviceManager<T> {
+        DeviceManager {
+            devices: vec![],
+            scheduler: CycleScheduler::default(),
+        }
+    }
+}
+
+impl DeviceManager {
+    /// Open every device with the vid, pid.
+    ///
+    /// Devices are named by serial number (or path if the
+    /// firmware does not set one). Fails if any of them can't be opened.
+    pub fn open_all(vid: u16, pid: u16) -> Result<DeviceManager, RidError> {
+
+        let mut hidapi = HidApi::new()?;
+        let mut manager = DeviceManager::default();
+
+        for info in hid::enumerate(vid, pid, &mut hidapi)? {
+
+            manager.add(&info.name(), HidTransport::open(&info)?);
+
+        }
+
+        Ok(manager)
+
+    }
+
+    /// Open the devices with the vid, pid and one of the serial numbers.
+    /// [RidError::NotFound] if any serial number is not connected.
+    pub fn open_serials(vid: u16, pid: u16, serial_numbers: &[&str]) -> Result<DeviceManager, RidError> {
+
+        let mut hidapi = HidApi::new()?;
+        let devices = hid::enumerate(vid, pid, &mut hidapi)?;
+
+        let mut manager = DeviceManager::default();
+
+        for serial_number in serial_numbers {
+
+            let info = devices
+                .iter()
+                .find(|info| info.serial_number.as_deref() == Some(*serial_number))
+                .ok_or(RidError::NotFound)?;
+
+            manager.add(serial_number, HidTransport::open(info)?);
+
+        }
+
+        Ok(manager)
+
+    }
+}
+
+impl<T: RidTransport> DeviceManager<T> {
+    /// Add a device, returns its index
+    pub fn add(&mut self, name: &str, transport: T) -> usize {
+
+        self.devices.push(RIDDevice::new(name, transport));
+
+        self.devices.len() - 1
+
+    }
+
+    /// Find a device by name
+    pub fn device(&mut self, name: &str) -> Option<&mut RIDDevice<T>> {
+
+        self.devices.iter_mut().find(|device| device.name == name)
+
+    }
+
+    /// Spin every device once, returns the number that replied
+    pub fn spin(&mut self) -> usize {
+
+        self.devices.iter_mut().filter_map(|device| device.session.spin().ok()).count()
+
+    }
+
+    /// Wait for the end of the cycle, every device
+    /// keeps its own system time.
+    pub fn timestep(&mut self) {
+
+        self.scheduler.wait();
+
+        for device in self.devices.iter_mut() {
+
+            device.session.layer.system_time = device.session.layer.host_now();
+
+        }
+
+    }
+}
@@ -1 +1,9 @@
+pub mod error;
 pub mod layer;
+pub mod manager;
+pub mod report;
+pub mod runtime;
+pub mod scheduler;
+pub mod session;
+pub mod sim;
+pub mod transport;
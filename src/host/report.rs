@@ -0,0 +1,268 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # PTP Report
+//!
+//!   Characterize PTP on any transport. [PtpReport::run()] spins a [RIDLayer]
+//! for a while and samples it, [PtpReport::save()] writes to a directory:
+//!
+//! - samples.csv: one row per sample
+//! - offset.gp, ptp_offset_err.gp, flight_time.gp, linear_conv.gp: gnuplot scripts
+//! - offset.png, ptp_offset_err.png, flight_time.png, linear_conv.png: rendered
+//!   if gnuplot is installed (run the scripts with gnuplot otherwise)
+//!
+//! ```
+//! use rid::host::{layer::RIDLayer, report::PtpReport, sim::{SimClient, SimConfig}, transport::loopback::LoopbackTransport};
+//!
+//! let (host, client) = LoopbackTransport::pair();
+//! let sim = SimClient::new(client, SimConfig::default()).spawn();
+//!
+//! let mut layer = RIDLayer::from_transport(host);
+//! layer.read_timeout = std::time::Duration::from_millis(1);
+//!
+//! let report = PtpReport::run(&mut layer, std::time::Duration::from_millis(100), 10).expect("Link failed");
+//! sim.stop();
+//!
+//! let dir = std::env::temp_dir().join("rid_ptp_report_doc");
+//! report.save(&dir).expect("Failed to write the report");
+//!
+//! assert!(dir.join("samples.csv").exists());
+//! ```
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    time,
+};
+
+use gnuplot::{Caption, Color, Figure};
+
+use crate::{
+    ptp::stats::PtpSnapshot,
+    host::{error::RidError, layer::RIDLayer, transport::RidTransport},
+};
+
+/// Size of the saved plots (pixels)
+pub const PLOT_SIZE: (u32, u32) = (800, 500);
+
+/// One sample of a [PtpReport], times in microseconds
+#[derive(Clone, Copy, Debug)]
+pub struct PtpSample {
+    /// Host time since the layer started
+    pub host_elapsed: i64,
+    /// Client time since the client's first reply
+    pub client_elapsed: i64,
+    /// Raw PTP offset of the exchange
+    pub offset: i64,
+    /// Host write to host read
+    pub flight_time: i64,
+    /// Host read minus the servo's conversion of the client write
+    pub host_error: i64,
+    /// Client write minus the servo's conversion of the host read
+    pub client_error: i64,
+    /// The client time at the end of the run, predicted with the fit at this sample
+    pub end_prediction: i64,
+    /// The client time at this sample, predicted with the fit at the end of the run
+    pub linear_client: i64,
+}
+
+/// Samples and statistics of a PTP characterization
+#[derive(Debug)]
+pub struct PtpReport {
+    /// Host time the run lasted (microseconds)
+    pub duration: i64,
+    /// Number of exchanges written
+    pub writes: usize,
+    /// Samples taken during the run
+    pub samples: Vec<PtpSample>,
+    /// Statistics at the end of the run
+    pub snapshot: PtpSnapshot,
+}
+
+impl PtpReport {
+    /// Spin the layer (one exchange per cycle) for a duration,
+    /// every `every`th cycle is sampled if it got a reply.
+    ///
+    /// Stops at the first error that isn't a [RidError::Timeout].
+    pub fn run<T: RidTransport>(layer: &mut RIDLayer<T>, duration: time::Duration, every: usize) -> Result<PtpReport, RidError> {
+
+        let duration = duration.as_micros() as i64;
+        let every = every.max(1);
+
+        let mut writes = 0;
+        let mut samples = vec![];
+
+        let start = layer.host_elapsed();
+
+        while layer.host_elapsed() - start < duration {
+
+            let flight_time = match layer.spin() {
+                Err(RidError::Timeout) => 0,
+                reply => reply?,
+            };
+
+            if flight_time > 0 && writes % every == 0 {
+
+                let (host_error, client_error) = layer.conversion_error();
+
+                samples.push(PtpSample {
+                    host_elapsed: layer.host_elapsed(),
+                    client_elapsed: layer.client_elapsed(),
+                    offset: layer.ptp_offset(),
+                    flight_time,
+                    host_error,
+                    client_error,
+                    end_prediction: layer.linear_to_client(start + duration),
+                    linear_client: 0,
+                });
+
+            }
+
+            writes += 1;
+
+            layer.timestep();
+
+        }
+
+        for sample in samples.iter_mut() {
+
+            sample.linear_client = layer.linear_to_client(sample.host_elapsed);
+
+        }
+
+        Ok(PtpReport {
+            duration,
+            writes,
+            samples,
+            snapshot: layer.snapshot(),
+        })
+
+    }
+
+    /// Write the samples as CSV, with a header row
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+
+        let mut file = BufWriter::new(File::create(path)?);
+
+        writeln!(file, "host_elapsed_us,client_elapsed_us,offset_us,flight_time_us,host_error_us,client_error_us,end_prediction_us,linear_client_us")?;
+
+        for s in self.samples.iter() {
+
+            writeln!(file, "{},{},{},{},{},{},{},{}",
+                s.host_elapsed,
+                s.client_elapsed,
+                s.offset,
+                s.flight_time,
+                s.host_error,
+                s.client_error,
+                s.end_prediction,
+                s.linear_client,
+            )?;
+
+        }
+
+        file.flush()
+
+    }
+
+    /// Build the offset, conversion error, flight time and linear conversion figures,
+    /// named by the files they save to
+    pub fn figures(&self) -> Vec<(&'static str, Figure)> {
+
+        let seconds = |f: fn(&PtpSample) -> i64| self.samples.iter().map(|s| f(s) as f64 / 1E6).collect::<Vec<f64>>();
+        let micros = |f: fn(&PtpSample) -> i64| self.samples.iter().map(|s| f(s) as f64).collect::<Vec<f64>>();
+
+        let x = seconds(|s| s.host_elapsed);
+        let n = self.samples.len();
+
+        let offset = &self.snapshot.stats.offset;
+
+        let mut fg = Figure::new();
+        fg.axes2d()
+            .lines(&x, micros(|s| s.offset), &[Caption("PTP Offset (microseconds)"), Color("black")])
+            .lines(&x, vec![offset.mean; n], &[Caption("Average"), Color("green")])
+            .lines(&x, vec![offset.mean + offset.std(); n], &[Caption("1 Sigma bound"), Color("red")])
+            .lines(&x, vec![offset.mean - offset.std(); n], &[Color("red")]);
+
+        let mut fg1 = Figure::new();
+        fg1.axes2d()
+            .lines(&x, micros(|s| s.host_error), &[Caption("Hr(t) - H(Cw(t)) (microseconds)"), Color("red")])
+            .lines(&x, micros(|s| s.client_error), &[Caption("Cw(t) - C(Hr(t))"), Color("blue")]);
+
+        let mut fg2 = Figure::new();
+        fg2.axes2d()
+            .lines(&x, micros(|s| s.flight_time), &[Caption("flight time (microseconds)"), Color("black")]);
+
+        let mut fg3 = Figure::new();
+        fg3.axes2d()
+            .lines(&x, seconds(|s| s.linear_client), &[Caption("C(t) = m * H(t) + b"), Color("red")])
+            .lines(&x, seconds(|s| s.client_elapsed), &[Caption("C(t) (seconds)"), Color("blue")])
+            .lines(&x, seconds(|s| s.end_prediction), &[Caption("C = (m * duration) + b"), Color("green")]);
+
+        vec![
+            ("offset", fg),
+            ("ptp_offset_err", fg1),
+            ("flight_time", fg2),
+            ("linear_conv", fg3),
+        ]
+
+    }
+
+    /// Write the CSV and a gnuplot script per figure to a directory (created if needed),
+    /// then try rendering the PNGs.
+    ///
+    /// Returns true if every PNG was written, errors writing the CSV or scripts are returned.
+    pub fn save(&self, dir: &Path) -> io::Result<bool> {
+
+        fs::create_dir_all(dir)?;
+
+        // the scripts keep the png paths, make them work from anywhere
+        let dir = fs::canonicalize(dir)?;
+
+        self.write_csv(&dir.join("samples.csv"))?;
+
+        let mut rendered = true;
+
+        for (name, mut figure) in self.figures() {
+
+            let png = dir.join(format!("{name}.png"));
+
+            // a png left by an earlier save would look rendered
+            match fs::remove_file(&png) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {},
+            }
+
+            figure.set_terminal(&format!("pngcairo size {},{}", PLOT_SIZE.0, PLOT_SIZE.1), &png.to_string_lossy());
+
+            // gnuplot's echo drops write errors, buffer the script and write it ourselves
+            let mut script = Vec::new();
+            figure.echo(&mut script);
+            fs::write(dir.join(format!("{name}.gp")), script)?;
+
+            // gnuplot writes the png as it exits, and a bad script still exits cleanly
+            if let Ok(mut gnuplot) = figure.show() {
+                let _ = gnuplot.wait();
+            }
+
+            rendered &= png.exists();
+            figure.close();
+
+        }
+
+        Ok(rendered)
+
+    }
+}
@@ -0,0 +1,196 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Threaded runtime
+//!
+//!   A [RidRuntime] moves a [RIDLayer] to its own thread. The thread writes one
+//! report per cycle, reads the reply and does the PTP stamping, so a slow
+//! application loop doesn't stretch the cycle.
+//!
+//! Reports queued on [RidRuntime::sender] go out in order, one per cycle, a PTP
+//! probe goes out when the queue is empty. A report that gets no reply (timeout
+//! or a lost link) is sent again the next cycle. Every reply comes back on
+//! [RidRuntime::receiver] as a [TimedReport]. Both queues hold [RUNTIME_QUEUE_DEPTH]
+//! reports, replies that don't fit are dropped and counted ([RidRuntime::dropped()]).
+//!
+//! ```
+//! use rid::host::{layer::RIDLayer, runtime::RidRuntime, sim::{SimClient, SimConfig}, transport::loopback::LoopbackTransport};
+//!
+//! let (host, client) = LoopbackTransport::pair();
+//! let sim = SimClient::new(client, SimConfig::default()).spawn();
+//!
+//! let mut layer = RIDLayer::from_transport(host);
+//! layer.read_timeout = std::time::Duration::from_millis(1);
+//!
+//! let runtime = RidRuntime::spawn(layer);
+//!
+//! let reply = runtime.receiver.recv().expect("Runtime stopped");
+//! assert!(reply.host_read > reply.host_write);
+//!
+//! let layer = runtime.stop().expect("Runtime failed");
+//! assert!(layer.stats.replies > 0);
+//! sim.stop();
+//! ```
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{Builder, JoinHandle},
+};
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+
+use crate::{
+    RIDReport,
+    ptp::{Micros, sync::SyncState},
+    rtnt::packet::RidPacket,
+    host::{layer::RIDLayer, transport::RidTransport},
+};
+
+/// Number of reports each [RidRuntime] queue holds
+pub const RUNTIME_QUEUE_DEPTH: usize = 64;
+
+/// Stack size of the [RidRuntime] and [crate::host::sim::SimClient] threads (bytes),
+/// leaves room for big reports ([crate::RID_PACKET_SIZE]) in unoptimized builds
+pub const RUNTIME_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// A reply from the client and when it happened
+#[derive(Clone, Copy, Debug)]
+pub struct TimedReport {
+    /// The reply
+    pub report: RIDReport,
+    /// Full host time of the report the client answered
+    pub host_write: Micros,
+    /// Full host time the reply was read at
+    pub host_read: Micros,
+    /// Full client time the reply was written at
+    pub client_write: Micros,
+    /// Host time the reply was written at, converted with the fit
+    pub host_time: Micros,
+    /// Sync state when the reply was read, see [RIDLayer::sync_state()]
+    pub sync: SyncState,
+    /// The link came back before this reply, the client lost its tasks
+    /// (see [crate::rtnt::task_manager::TaskManager::reconnect()])
+    pub reconnected: bool,
+}
+
+/// Handle to a [RIDLayer] running on a thread.
+/// Dropping the handle stops the thread.
+pub struct RidRuntime<T: RidTransport> {
+    /// Queue a report to write
+    pub sender: Sender<RIDReport>,
+    /// Replies from the client
+    pub receiver: Receiver<TimedReport>,
+
+    dropped: Arc<AtomicUsize>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<RIDLayer<T>>>,
+}
+
+impl<T: RidTransport + Send + 'static> RidRuntime<T> {
+    /// Run the layer on its own thread until the runtime is stopped
+    pub fn spawn(mut layer: RIDLayer<T>) -> RidRuntime<T> {
+
+        let (sender, outgoing) = bounded::<RIDReport>(RUNTIME_QUEUE_DEPTH);
+        let (incoming, receiver) = bounded::<TimedReport>(RUNTIME_QUEUE_DEPTH);
+
+        let running = Arc::new(AtomicBool::new(true));
+        let dropped = Arc::new(AtomicUsize::new(0));
+
+        let flag = running.clone();
+        let drops = dropped.clone();
+
+        let thread = Builder::new().name("rid-runtime".to_string()).stack_size(RUNTIME_STACK_SIZE).spawn(move || {
+
+            // the queued report that has not been answered yet
+            let mut pending: Option<RIDReport> = None;
+
+            while flag.load(Ordering::Relaxed) {
+
+                let report = pending.take().or_else(|| outgoing.try_recv().ok());
+                let mut buffer = report.unwrap_or(RidPacket::Ptp.encode());
+
+                match layer.transfer(&mut buffer) {
+                    Ok(_) => {
+
+                        let reply = TimedReport {
+                            report: buffer,
+                            host_write: layer.host_write,
+                            host_read: layer.host_read,
+                            client_write: layer.client_write,
+                            host_time: layer.estimator.fit().to_host(layer.client_write),
+                            sync: layer.sync_state(),
+                            reconnected: layer.reconnected(),
+                        };
+
+                        if let Err(TrySendError::Full(_)) = incoming.try_send(reply) {
+                            drops.fetch_add(1, Ordering::Relaxed);
+                        }
+
+                    },
+                    Err(_) => pending = report,
+                }
+
+                layer.timestep();
+
+            }
+
+            layer
+
+        }).expect("Failed to spawn the runtime thread");
+
+        RidRuntime {
+            sender,
+            receiver,
+
+            dropped,
+            running,
+            thread: Some(thread),
+        }
+
+    }
+}
+
+impl<T: RidTransport> RidRuntime<T> {
+    /// Number of replies dropped because [RidRuntime::receiver] was full
+    pub fn dropped(&self) -> usize {
+
+        self.dropped.load(Ordering::Relaxed)
+
+    }
+
+    /// Stop the thread and get the layer back
+    pub fn stop(mut self) -> Option<RIDLayer<T>> {
+
+        self.running.store(false, Ordering::Relaxed);
+
+        self.thread.take().and_then(|thread| thread.join().ok())
+
+    }
+}
+
+impl<T: RidTransport> Drop for RidRuntime<T> {
+    /// Stop the thread, the layer is dropped with it
+    fn drop(&mut self) {
+
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+    }
+}
@@ -0,0 +1,153 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Cycle Scheduler
+//!
+//!   Paces a loop at a fixed period. Deadlines are absolute, cycle n ends at
+//! start + (n + 1) * period, so a late cycle doesn't push the ones after it.
+//! [CycleScheduler::wait()] sleeps until [CycleScheduler::spin] before the
+//! deadline (sleeping overshoots by tens of microseconds) and spins the rest.
+//!
+//! A cycle that overran its deadline is counted in [CycleScheduler::missed],
+//! the deadlines it covered are skipped instead of run back to back.
+//! How late each wake up was goes in [CycleScheduler::jitter].
+//!
+//! ```
+//! use std::time::{Duration, Instant};
+//! use rid::host::scheduler::CycleScheduler;
+//!
+//! let mut scheduler = CycleScheduler::new(Duration::from_millis(1));
+//! let start = Instant::now();
+//!
+//! for _ in 0..20 {
+//!     scheduler.wait();
+//! }
+//!
+//! assert!(start.elapsed() >= Duration::from_millis(19));
+//! assert!(scheduler.cycles() >= 20);
+//! ```
+
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use crate::{RID_CYCLE_TIME_US, ptp::stats::RunningStats};
+
+/// Default time spun before a deadline (microseconds)
+pub const SCHEDULER_SPIN_US: u64 = 200;
+
+/// Waits for absolute deadlines a period apart
+#[derive(Clone, Copy, Debug)]
+pub struct CycleScheduler {
+    /// Time between deadlines
+    pub period: Duration,
+    /// The wait spins (instead of sleeping) for this long before a deadline
+    pub spin: Duration,
+    /// Number of cycles that ended after their deadline
+    pub missed: usize,
+    /// How late each wake up was (microseconds)
+    pub jitter: RunningStats,
+
+    start: Instant,
+    cycle: u64,
+}
+
+impl Default for CycleScheduler {
+    /// A scheduler at [RID_CYCLE_TIME_US]
+    fn default() -> CycleScheduler {
+
+        CycleScheduler::new(Duration::from_micros(RID_CYCLE_TIME_US as u64))
+
+    }
+}
+
+impl CycleScheduler {
+    /// Create a scheduler, the first deadline is a period from now
+    pub fn new(period: Duration) -> CycleScheduler {
+
+        CycleScheduler {
+            period,
+            spin: Duration::from_micros(SCHEDULER_SPIN_US),
+            missed: 0,
+            jitter: RunningStats::default(),
+
+            start: Instant::now(),
+            cycle: 0,
+        }
+
+    }
+
+    /// Start over from now, the statistics are cleared
+    pub fn reset(&mut self) {
+
+        *self = CycleScheduler {
+            spin: self.spin,
+            ..CycleScheduler::new(self.period)
+        };
+
+    }
+
+    /// The end of the current cycle
+    pub fn deadline(&self) -> Instant {
+
+        self.start + self.period.mul_f64((self.cycle + 1) as f64)
+
+    }
+
+    /// Wait for the end of the current cycle. A missed deadline
+    /// returns right away and moves to the next deadline still ahead.
+    ///
+    /// Returns the deadline that ended the cycle.
+    pub fn wait(&mut self) -> Instant {
+
+        let deadline = self.deadline();
+        let now = Instant::now();
+
+        if now > deadline {
+
+            self.missed += 1;
+
+            // skip every deadline that already passed
+            let behind = (now - self.start).as_nanos() / self.period.as_nanos().max(1);
+            self.cycle = behind as u64;
+
+            return deadline;
+
+        }
+
+        if deadline - now > self.spin {
+            sleep(deadline - now - self.spin);
+        }
+
+        let mut woke = Instant::now();
+
+        while woke < deadline {
+            woke = Instant::now();
+        }
+
+        self.jitter.add((woke - deadline).as_nanos() as f64 / 1E3);
+        self.cycle += 1;
+
+        deadline
+
+    }
+
+    /// Number of cycles since the start, missed ones included
+    pub fn cycles(&self) -> u64 {
+
+        self.cycle
+
+    }
+}
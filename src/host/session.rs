@@ -0,0 +1,213 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Host session
+//!
+//!   The entry point of a host program. A [HostSession] owns the link to one
+//! client ([RIDLayer]) and the host [TaskManager]. Each [HostSession::tick()]
+//! sends the next control packet (or a PTP probe), collects the reply and
+//! waits for the end of the cycle. Tasks are loaded with [HostSession::load()],
+//! stopped with [HostSession::kill()] and followed with [HostSession::status()].
+//!
+//! ```
+//! use rid::{
+//!     host::{session::HostSession, sim::{SimClient, SimConfig}, transport::loopback::LoopbackTransport},
+//!     rtnt::{TaskStatus, constant::RTConstant, task_generator::{TaskDriver, TaskExecutable}, task_manager::TaskNode},
+//! };
+//!
+//! let (host, client) = LoopbackTransport::pair();
+//! let sim = SimClient::new(client, SimConfig::default()).spawn();
+//!
+//! let mut session = HostSession::new(host);
+//! session.layer.read_timeout = std::time::Duration::from_millis(10);
+//!
+//! let task = TaskExecutable::Constant(RTConstant::new(1.5));
+//! let index = session.load(TaskNode::new(1, 100, 0, 1, TaskDriver::Constant, task)).expect("Too many tasks");
+//!
+//! while session.status().tasks[index] != TaskStatus::Active {
+//!     session.tick().expect("Client did not reply");
+//! }
+//!
+//! sim.stop();
+//! ```
+
+use crate::{
+    ptp::{Micros, USEC_PER_HOUR, sync::SyncState},
+    rtnt::{
+        MAX_TASKS, TaskStatus,
+        hello::{Capabilities, ConfigError},
+        packet::RidPacket,
+        task_manager::{TaskManager, TaskNode},
+        trigger::TriggerAction,
+    },
+    host::{
+        error::RidError,
+        layer::RIDLayer,
+        transport::{RidTransport, hid::HidTransport},
+    },
+};
+
+/// What a [HostSession] knows about its client
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionStatus {
+    /// The link is up, see [RIDLayer::connected]
+    pub connected: bool,
+    /// Capabilities of the client, None until the handshake completes
+    pub remote: Option<Capabilities>,
+    /// Why the client can't run the loaded tasks
+    pub config_error: Option<ConfigError>,
+    /// Whether time conversions can be trusted
+    pub sync: SyncState,
+    /// Status of each loaded task, by index
+    pub tasks: Vec<TaskStatus>,
+    /// Number of cycles the client replied in
+    pub replies: usize,
+}
+
+impl SessionStatus {
+    /// True if every loaded task runs on the client
+    pub fn is_active(&self) -> bool {
+
+        self.tasks.iter().all(|status| *status == TaskStatus::Active)
+
+    }
+}
+
+/// A link to one client and the tasks the host runs on it
+pub struct HostSession<T: RidTransport = HidTransport> {
+    /// Link and PTP state of the client
+    pub layer: RIDLayer<T>,
+    /// Host side tasks of the client
+    pub tasks: TaskManager,
+    /// Number of cycles the client replied in
+    pub replies: usize,
+}
+
+impl HostSession {
+    /// Open a session with the HID device with the vid, pid,
+    /// see [HidTransport::new()] for the errors.
+    pub fn open(vid: u16, pid: u16) -> Result<HostSession, RidError> {
+
+        Ok(HostSession::new(HidTransport::new(vid, pid)?))
+
+    }
+}
+
+impl<T: RidTransport> HostSession<T> {
+    /// Create a session with no tasks on top of a transport
+    pub fn new(transport: T) -> HostSession<T> {
+
+        HostSession {
+            layer: RIDLayer::from_transport(transport),
+            tasks: TaskManager::default(),
+            replies: 0,
+        }
+
+    }
+
+    /// Run one cycle without waiting: send the next control packet (or a PTP
+    /// probe when there is nothing to configure), then collect the reply.
+    /// The tasks are pushed again after the client reconnects.
+    ///
+    /// Returns the flight time of the reply (microseconds), see [RIDLayer::transfer()].
+    pub fn spin(&mut self) -> Result<i64, RidError> {
+
+        let mut buffer = self.tasks.control_spin().unwrap_or(RidPacket::Ptp.encode());
+
+        let flight_time = self.layer.transfer(&mut buffer)?;
+
+        if self.layer.reconnected() {
+            self.tasks.reconnect();
+        }
+
+        self.tasks.collect(&buffer);
+        self.replies += 1;
+
+        Ok(flight_time)
+
+    }
+
+    /// [HostSession::spin()] then wait for the end of the cycle
+    /// ([RIDLayer::timestep()]), the wait happens on errors too.
+    pub fn tick(&mut self) -> Result<i64, RidError> {
+
+        let flight_time = self.spin();
+
+        self.layer.timestep();
+
+        flight_time
+
+    }
+
+    /// Load a task, it is configured on the client over the next ticks.
+    ///
+    /// Returns the index of the task, None if [MAX_TASKS] are loaded.
+    pub fn load(&mut self, node: TaskNode) -> Option<usize> {
+
+        let index = self.tasks.n_nodes;
+
+        match index < MAX_TASKS {
+            true => {
+
+                self.tasks.init_node(node);
+                Some(index)
+
+            },
+            false => None,
+        }
+
+    }
+
+    /// Kill every task, on the client and the host. The next
+    /// tick sends the kill and unloads the tasks.
+    pub fn kill(&mut self) {
+
+        self.tasks.panic_all();
+
+    }
+
+    /// The link, handshake, sync and task state
+    pub fn status(&self) -> SessionStatus {
+
+        SessionStatus {
+            connected: self.layer.connected,
+            remote: self.tasks.remote,
+            config_error: self.tasks.config_error,
+            sync: self.layer.sync_state(),
+            tasks: self.tasks.nodes[..self.tasks.n_nodes].iter().map(|node| node.status).collect(),
+            replies: self.replies,
+        }
+
+    }
+
+    /// Queue a trigger for the client, see [TaskManager::trigger()].
+    /// A time that passed runs as soon as the client gets it.
+    ///
+    /// Returns how far from `at` the client may run it (microseconds,
+    /// [RIDLayer::trigger_bound()]), None if the trigger queue is full or
+    /// `at` is half an hour or more away, see [crate::rtnt::trigger].
+    pub fn trigger(&mut self, at: Micros, action: TriggerAction, tasks: u32) -> Option<i64> {
+
+        let at = at.max(self.layer.host_now());
+
+        if at - self.layer.host_now() >= (USEC_PER_HOUR / 2) as i64 {
+            return None;
+        }
+
+        self.tasks.trigger(at, action, tasks)?;
+
+        Some(self.layer.trigger_bound(at))
+
+    }
+}
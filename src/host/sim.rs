@@ -0,0 +1,285 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Simulated RID client
+//!
+//!   Runs the client half of the [TimeStamp] protocol against a
+//! [VirtualClock] so PTP can be validated without hardware. The clock
+//! can start at any offset, run fast or slow (skew) and roll over at
+//! a chosen period. Flight times get random jitter in both directions.
+//!
+//!   The host layer and [ClientClock] expect the client's stamps to roll
+//! over every hour ([USEC_PER_HOUR], the default). A shorter period plays
+//! firmware whose counter wraps early, the host sees the stamps jump.
+//! The client estimates host time with a [ClientClock] like firmware would
+//! and runs triggers at that time.
+//!
+//!   Every report is passed to a client [TaskManager], replies come
+//! from [TaskManager::spin()], so the simulation also hosts tasks.
+//!
+//! ```
+//! use rid::host::{layer::RIDLayer, sim::{SimClient, SimConfig}, transport::loopback::LoopbackTransport};
+//!
+//! let (host, client) = LoopbackTransport::pair();
+//! let _sim = SimClient::new(client, SimConfig::default()).spawn();
+//!
+//! let mut layer = RIDLayer::from_transport(host);
+//! layer.read_timeout = std::time::Duration::from_millis(10);
+//! layer.spin().expect("Simulated client did not reply");
+//! ```
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    RIDReport, RID_PACKET_SIZE,
+    ptp::{TimeStamp, USEC_PER_HOUR, clock::{ClientClock, ClockSource}},
+    rtnt::task_manager::TaskManager,
+    host::{runtime::RUNTIME_STACK_SIZE, transport::RidTransport},
+};
+
+/// Configuration of a [SimClient]
+pub struct SimConfig {
+    /// Client time when the simulation starts (microseconds)
+    pub offset: u32,
+    /// Client clock rate error in parts per million, 
+    /// positive values make the client run fast
+    pub skew_ppm: f64,
+    /// Period the client clock rolls over at (microseconds), at most
+    /// an hour ([USEC_PER_HOUR]). The host layer expects an hour
+    pub rollover: u32,
+    /// Maximum random delay added to each packet flight (microseconds)
+    pub jitter_us: u32,
+    /// Seed for the jitter generator
+    pub seed: u64,
+}
+
+impl Default for SimConfig {
+    /// A client that starts near the end of the hour, runs slightly
+    /// fast and adds a small amount of flight time jitter.
+    fn default() -> SimConfig {
+        SimConfig {
+            offset: (USEC_PER_HOUR - 3_000_000) as u32,
+            skew_ppm: 50.0,
+            rollover: USEC_PER_HOUR as u32,
+            jitter_us: 50,
+            seed: 0x5EED,
+        }
+    }
+}
+
+/// A clock that drifts from real time
+pub struct VirtualClock {
+    /// Client time at start (microseconds)
+    pub offset: u32,
+    /// Rate error in parts per million
+    pub skew_ppm: f64,
+    /// Roll over period (microseconds)
+    pub rollover: u32,
+    /// Real time the clock started at
+    pub start: Instant,
+}
+
+impl VirtualClock {
+    /// Start a new clock.
+    ///
+    /// Panics if the rollover is 0 or longer than an hour,
+    /// a [TimeStamp] can't carry a longer period.
+    pub fn new(offset: u32, skew_ppm: f64, rollover: u32) -> VirtualClock {
+
+        assert!(rollover > 0 && rollover as u64 <= USEC_PER_HOUR, "Rollover must be within an hour, got {rollover}us");
+
+        VirtualClock {
+            offset,
+            skew_ppm,
+            rollover,
+            start: Instant::now(),
+        }
+
+    }
+}
+
+impl ClockSource for VirtualClock {
+    /// Read the clock in microseconds, wraps at the rollover period
+    fn micros(&self) -> u32 {
+
+        let elapsed = self.start.elapsed().as_nanos() as f64 / 1_000.0;
+        let t = self.offset as f64 + (elapsed * (1.0 + (self.skew_ppm * 1E-6)));
+
+        (t as u64 % self.rollover as u64) as u32
+
+    }
+}
+
+/// Simulated client device
+pub struct SimClient<T: RidTransport> {
+    /// The link to the host
+    pub transport: T,
+    /// The clients system time and its estimate of the host's
+    pub clock: ClientClock<VirtualClock>,
+    /// ['TimeStamp'] for synchronization
+    pub ptp_stamp: TimeStamp,
+    /// The clients tasks
+    pub tasks: TaskManager,
+    /// Maximum random delay added to each packet flight (microseconds)
+    pub jitter_us: u32,
+    /// Number of reports answered
+    pub replies: usize,
+
+    rng: u64,
+}
+
+impl<T: RidTransport> SimClient<T> {
+    /// Create a client that talks over the transport
+    pub fn new(transport: T, config: SimConfig) -> SimClient<T> {
+        SimClient {
+            transport,
+            clock: ClientClock::new(VirtualClock::new(config.offset, config.skew_ppm, config.rollover)),
+            ptp_stamp: TimeStamp::new(0, 0, 0, 0),
+            tasks: TaskManager::default(),
+            jitter_us: config.jitter_us,
+            replies: 0,
+
+            rng: config.seed | 1,
+        }
+    }
+
+    /// xorshift, good enough for jitter
+    fn jitter(&mut self) -> Duration {
+
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+
+        match self.jitter_us {
+            0 => Duration::ZERO,
+            max => Duration::from_micros(self.rng % max as u64),
+        }
+
+    }
+
+    /// Hold the thread to simulate extra flight time.
+    /// Spins because sleeping is too coarse.
+    fn fly(&mut self) {
+
+        let flight = self.jitter();
+        let t = Instant::now();
+
+        while t.elapsed() < flight {}
+
+    }
+
+    /// Wait for a report from the host and answer it.
+    /// Returns true if a reply was sent.
+    pub fn step(&mut self, timeout: Duration) -> bool {
+
+        let mut buffer = [0u8; RID_PACKET_SIZE];
+
+        match self.transport.recv(&mut buffer, timeout) {
+            Ok(()) => {
+
+                self.fly();
+                self.ptp_stamp.client_read(&buffer, self.clock.micros());
+                self.clock.sample(&self.ptp_stamp);
+
+                self.tasks.collect(&buffer);
+                self.tasks.host_time = self.clock.is_synced().then(|| self.clock.host_now());
+
+                let mut reply: RIDReport = match self.tasks.spin() {
+                    Some(report) => report,
+                    None => {
+
+                        let mut idle = [0u8; RID_PACKET_SIZE];
+                        self.tasks.stamp_sequence(&mut idle);
+
+                        idle
+
+                    },
+                };
+
+                self.ptp_stamp.client_stamp(&mut reply, self.clock.micros());
+                self.fly();
+
+                self.replies += 1;
+
+                self.transport.send(&reply).is_ok()
+
+            },
+            Err(_) => false,
+        }
+
+    }
+}
+
+impl<T: RidTransport + Send + 'static> SimClient<T> {
+    /// Run the client on its own thread until the returned
+    /// [SimHandle] is stopped or dropped.
+    pub fn spawn(mut self) -> SimHandle<T> {
+
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+
+        let thread = Builder::new().name("rid-sim".to_string()).stack_size(RUNTIME_STACK_SIZE).spawn(move || {
+
+            while flag.load(Ordering::Relaxed) {
+
+                self.step(Duration::from_millis(1));
+
+            }
+
+            self
+
+        }).expect("Failed to spawn the sim thread");
+
+        SimHandle {
+            running,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Handle to a [SimClient] running on a thread
+pub struct SimHandle<T: RidTransport> {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<SimClient<T>>>,
+}
+
+impl<T: RidTransport> SimHandle<T> {
+    /// Stop the simulation and get the client back
+    pub fn stop(mut self) -> Option<SimClient<T>> {
+
+        self.running.store(false, Ordering::Relaxed);
+
+        self.thread.take().and_then(|thread| thread.join().ok())
+
+    }
+}
+
+impl<T: RidTransport> Drop for SimHandle<T> {
+    fn drop(&mut self) {
+
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+    }
+}
@@ -0,0 +1,219 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # HidApi transport for RID comms
+
+extern crate hidapi;
+use hidapi::{HidApi, HidDevice, HidError};
+
+use std::{
+    ffi::{CStr, CString},
+    time::Duration,
+};
+
+use crate::{
+    RIDReport, RID_PACKET_SIZE,
+    host::{error::RidError, transport::RidTransport},
+};
+
+/// helper function to create a new HidDevice
+/// not really relevant since monothread
+pub fn new_device(vid: u16, pid: u16, hidapi: &mut HidApi) -> Result<HidDevice, RidError> {
+        
+    let device = hidapi.open(vid, pid).map_err(|_| open_error(vid, pid, None, hidapi))?;
+            
+    device.set_blocking_mode(false)?;
+
+    Ok(device)
+}
+
+/// Why a device could not be opened. hidapi only has a message
+/// for it, so look for the device: [RidError::PermissionDenied] if it
+/// is connected (usually the udev rules), [RidError::NotFound] if not.
+fn open_error(vid: u16, pid: u16, path: Option<&CStr>, hidapi: &mut HidApi) -> RidError {
+
+    match enumerate(vid, pid, hidapi) {
+        Ok(devices) => match devices.iter().any(|info| path.is_none_or(|path| info.path.as_c_str() == path)) {
+            true => RidError::PermissionDenied,
+            false => RidError::NotFound,
+        },
+        Err(e) => e,
+    }
+
+}
+
+/// Errors of an open device, hidapi fails reads and writes
+/// once the device is unplugged
+fn link_error(e: HidError) -> RidError {
+
+    match RidError::from(e) {
+        e @ (RidError::Timeout | RidError::ShortWrite(_)) => e,
+        _ => RidError::Disconnected,
+    }
+
+}
+/// A HID device found by [enumerate()]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// USB device vid
+    pub vid: u16,
+    /// USB device pid
+    pub pid: u16,
+    /// Platform path of the device, unique per interface
+    pub path: CString,
+    /// Serial number, unique per device if the firmware sets one
+    pub serial_number: Option<String>,
+    /// Product string
+    pub product: Option<String>,
+}
+
+impl DeviceInfo {
+    /// A name for logs, the serial number or the path
+    pub fn name(&self) -> String {
+
+        match &self.serial_number {
+            Some(serial_number) => serial_number.clone(),
+            None => self.path.to_string_lossy().into_owned(),
+        }
+
+    }
+}
+
+/// List every connected device with the vid, pid.
+///
+/// Refreshes the device list of the [HidApi] first, so
+/// this also picks up devices plugged in after it was created.
+pub fn enumerate(vid: u16, pid: u16, hidapi: &mut HidApi) -> Result<Vec<DeviceInfo>, RidError> {
+
+    hidapi.refresh_devices()?;
+
+    let devices = hidapi.device_list()
+        .filter(|info| info.vendor_id() == vid && info.product_id() == pid)
+        .map(|info| DeviceInfo {
+            vid,
+            pid,
+            path: info.path().to_owned(),
+            serial_number: info.serial_number().map(|s| s.to_string()),
+            product: info.product_string().map(|s| s.to_string()),
+        })
+        .collect();
+
+    Ok(devices)
+
+}
+
+/// [RidTransport] for a USB HID device
+pub struct HidTransport {
+    /// USB device vid
+    pub vid: u16,
+    /// USB device pid
+    pub pid: u16,
+    /// Serial number of the device, [RidTransport::reconnect()]
+    /// looks for it so a replug opens the same device
+    pub serial_number: Option<String>,
+
+    /// USB hidapi (C wrapper lib)
+    pub hidapi: HidApi,
+    /// USB HidDevice class
+    pub device: HidDevice,
+}
+
+impl HidTransport {
+    /// Connects to the device with the specified vid, pid.
+    /// Fails with [RidError::NotFound] if the device cant be found.
+    /// On [RidError::PermissionDenied] check your udev rules and
+    /// make sure they include the vid pid.
+    pub fn new(vid: u16, pid: u16) -> Result<HidTransport, RidError> {
+
+        let mut hidapi = HidApi::new()?;
+        let device = new_device(vid, pid, &mut hidapi)?;
+        let serial_number = device.get_serial_number_string().ok().flatten();
+
+        Ok(HidTransport {
+            vid,
+            pid,
+            serial_number,
+
+            hidapi,
+            device,
+        })
+    }
+
+    /// Connects to a device found by [enumerate()].
+    /// Use this when several devices share a vid, pid.
+    pub fn open(info: &DeviceInfo) -> Result<HidTransport, RidError> {
+
+        let mut hidapi = HidApi::new()?;
+        let device = hidapi.open_path(&info.path).map_err(|_| open_error(info.vid, info.pid, Some(&info.path), &mut hidapi))?;
+
+        device.set_blocking_mode(false)?;
+
+        Ok(HidTransport {
+            vid: info.vid,
+            pid: info.pid,
+            serial_number: info.serial_number.clone(),
+
+            hidapi,
+            device,
+        })
+    }
+}
+
+impl RidTransport for HidTransport {
+
+    fn send(&mut self, buffer: &RIDReport) -> Result<(), RidError> {
+
+        match self.device.write(buffer).map_err(link_error)? {
+            n if n >= RID_PACKET_SIZE => Ok(()),
+            n => Err(RidError::ShortWrite(n)),
+        }
+
+    }
+
+    fn recv(&mut self, buffer: &mut RIDReport, timeout: Duration) -> Result<(), RidError> {
+
+        match self.device.read_timeout(buffer, timeout.as_millis() as i32).map_err(link_error)? {
+            0 => Err(RidError::Timeout),
+            _ => Ok(()),
+        }
+
+    }
+
+    /// Reopen the device after it was unplugged. Looks for the same
+    /// serial number, or any device with the vid, pid without one.
+    fn reconnect(&mut self) -> Result<(), RidError> {
+
+        let info = enumerate(self.vid, self.pid, &mut self.hidapi)?
+            .into_iter()
+            .find(|info| self.serial_number.is_none() || info.serial_number == self.serial_number)
+            .ok_or(RidError::NotFound)?;
+
+        // os errors keep their kind, hidapi's own errors stay Other
+        // unless the device went away again since it was listed
+        let device = self.hidapi.open_path(&info.path).map_err(|e| match RidError::from(e) {
+            RidError::Other(message) => match open_error(self.vid, self.pid, Some(&info.path), &mut self.hidapi) {
+                RidError::NotFound => RidError::NotFound,
+                _ => RidError::Other(message),
+            },
+            e => e,
+        })?;
+
+        device.set_blocking_mode(false)?;
+
+        self.device = device;
+
+        Ok(())
+
+    }
+}
@@ -0,0 +1,77 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # In-memory loopback transport
+//!
+//!   Two connected ends of a channel, whatever one end sends
+//! the other end receives. Useful for simulators and tests.
+//! Dropping one end disconnects the other.
+
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender, TryRecvError};
+
+use std::time::Duration;
+
+use crate::{
+    RIDReport,
+    host::{error::RidError, transport::RidTransport},
+};
+
+/// One end of an in-memory [RidTransport] pair
+pub struct LoopbackTransport {
+    tx: Sender<RIDReport>,
+    rx: Receiver<RIDReport>,
+}
+
+impl LoopbackTransport {
+    /// Create two connected ends, give one to the host and
+    /// the other to the client.
+    pub fn pair() -> (LoopbackTransport, LoopbackTransport) {
+
+        let (host_tx, client_rx) = unbounded();
+        let (client_tx, host_rx) = unbounded();
+
+        (
+            LoopbackTransport { tx: host_tx, rx: host_rx },
+            LoopbackTransport { tx: client_tx, rx: client_rx },
+        )
+    }
+}
+
+impl RidTransport for LoopbackTransport {
+
+    fn send(&mut self, buffer: &RIDReport) -> Result<(), RidError> {
+
+        self.tx.send(*buffer).map_err(|_| RidError::Disconnected)
+
+    }
+
+    fn recv(&mut self, buffer: &mut RIDReport, timeout: Duration) -> Result<(), RidError> {
+
+        let report = match timeout.is_zero() {
+            true => self.rx.try_recv().map_err(|e| match e {
+                TryRecvError::Empty => RidError::Timeout,
+                TryRecvError::Disconnected => RidError::Disconnected,
+            }),
+            false => self.rx.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => RidError::Timeout,
+                RecvTimeoutError::Disconnected => RidError::Disconnected,
+            }),
+        }?;
+
+        buffer.copy_from_slice(&report);
+
+        Ok(())
+
+    }
+}
@@ -0,0 +1,50 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Transports for RID reports
+//!
+//!   A [RidTransport] moves [RIDReport]s between a host and a client.
+//! The [crate::host::layer::RIDLayer] is generic over the transport, so
+//! the same PTP and timing logic can drive hardware, simulators and tests.
+//!
+
+use std::time::Duration;
+
+use crate::{RIDReport, host::error::RidError};
+
+/// Trait for anything that can carry [RIDReport]s
+pub trait RidTransport {
+
+    /// Send a report. Writing less than a whole
+    /// report is a [RidError::ShortWrite].
+    fn send(&mut self, buffer: &RIDReport) -> Result<(), RidError>;
+
+    /// Receive a report into the buffer, waits at most timeout.
+    /// [RidError::Timeout] if nothing arrived.
+    fn recv(&mut self, buffer: &mut RIDReport, timeout: Duration) -> Result<(), RidError>;
+
+    /// Try opening the link again after a [RidError::Disconnected],
+    /// the default can't and stays disconnected.
+    fn reconnect(&mut self) -> Result<(), RidError> {
+
+        Err(RidError::Disconnected)
+
+    }
+
+}
+
+pub mod hid;
+pub mod loopback;
+pub mod serial;
+pub mod udp;
@@ -0,0 +1,133 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Byte stream transport for RID comms
+//!
+//!   Frames [RIDReport]s with [crate::cobs] so the RTNT and PTP protocol
+//! can run over USB-serial (CDC-ACM), UART or any other byte stream.
+//! Each frame carries a CRC-16 of the report, frames that fail it are
+//! dropped like partial frames (see [SerialTransport::dropped()]).
+//! Works with any [Read] + [Write] object, a tty opened as a 
+//! [std::fs::File], a pty or a socket.
+//!
+//!   Streams should be non-blocking or have a read timeout set,
+//! otherwise [RidTransport::recv] can block past its timeout. A stream
+//! that ends (the other side closed it) is [RidError::Disconnected].
+
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read, Write},
+    thread::yield_now,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    RIDReport,
+    cobs::{encode, CobsDecoder, COBS_FRAME_SIZE},
+    host::{error::RidError, transport::RidTransport},
+};
+
+/// [RidTransport] over a COBS framed byte stream
+pub struct SerialTransport<S: Read + Write> {
+    /// The byte stream
+    pub stream: S,
+    /// Frame decoder
+    pub decoder: CobsDecoder,
+    /// Bytes read past the end of the last frame
+    backlog: VecDeque<u8>,
+}
+
+impl<S: Read + Write> SerialTransport<S> {
+    /// Create a transport on top of a stream
+    pub fn new(stream: S) -> SerialTransport<S> {
+        SerialTransport {
+            stream,
+            decoder: CobsDecoder::default(),
+            backlog: VecDeque::new(),
+        }
+    }
+
+    /// Number of partial or corrupted frames dropped
+    pub fn dropped(&self) -> usize {
+
+        self.decoder.dropped
+
+    }
+
+    /// Feed the backlog through the decoder until a report comes out
+    fn decode_backlog(&mut self, buffer: &mut RIDReport) -> bool {
+
+        while let Some(byte) = self.backlog.pop_front() {
+
+            if let Some(report) = self.decoder.push(byte) {
+
+                buffer.copy_from_slice(&report);
+                return true;
+
+            }
+        }
+
+        false
+
+    }
+}
+
+impl<S: Read + Write> RidTransport for SerialTransport<S> {
+
+    fn send(&mut self, buffer: &RIDReport) -> Result<(), RidError> {
+
+        let mut frame = [0u8; COBS_FRAME_SIZE];
+        let length = encode(buffer, &mut frame);
+
+        self.stream.write_all(&frame[..length])?;
+        self.stream.flush()?;
+
+        Ok(())
+
+    }
+
+    fn recv(&mut self, buffer: &mut RIDReport, timeout: Duration) -> Result<(), RidError> {
+
+        let deadline = Instant::now() + timeout;
+        let mut bytes = [0u8; COBS_FRAME_SIZE];
+
+        loop {
+
+            if self.decode_backlog(buffer) {
+                return Ok(());
+            }
+
+            match self.stream.read(&mut bytes) {
+                Ok(0) => return Err(RidError::Disconnected),
+                Ok(n) => self.backlog.extend(&bytes[..n]),
+                Err(e) => match e.kind() {
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted => yield_now(),
+                    _ => return Err(e.into()),
+                },
+            }
+
+            // a stream of garbage never runs dry, stop at the deadline anyway
+            if Instant::now() >= deadline {
+
+                return match self.decode_backlog(buffer) {
+                    true => Ok(()),
+                    false => Err(RidError::Timeout),
+                };
+
+            }
+
+        }
+
+    }
+}
@@ -0,0 +1,125 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # UDP transport for RID comms
+//!
+//!   Each datagram carries exactly one [RIDReport]. Datagrams of any
+//! other size are dropped. A host connects to a known client address,
+//! a client can listen and reply to whoever wrote to it last. Sending
+//! before a listener heard from anyone is [RidError::Disconnected].
+
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    RIDReport, RID_PACKET_SIZE,
+    host::{error::RidError, transport::RidTransport},
+};
+
+/// [RidTransport] over a UDP socket
+pub struct UdpTransport {
+    /// The bound socket
+    pub socket: UdpSocket,
+    /// Where reports are sent, learned from
+    /// incoming reports when listening
+    pub peer: Option<SocketAddr>,
+}
+
+impl UdpTransport {
+    /// Bind to a local address and send reports to a remote one.
+    /// Fails if the socket can't be bound or the remote 
+    /// address does not resolve ([RidError::NotFound]).
+    pub fn connect<A: ToSocketAddrs, B: ToSocketAddrs>(local: A, remote: B) -> Result<UdpTransport, RidError> {
+
+        let socket = UdpSocket::bind(local)?;
+        let peer = remote.to_socket_addrs()?.next().ok_or(RidError::NotFound)?;
+
+        Ok(UdpTransport {
+            socket,
+            peer: Some(peer),
+        })
+    }
+
+    /// Bind to a local address and wait for a peer.
+    /// Replies go to the last address a report came from.
+    pub fn listen<A: ToSocketAddrs>(local: A) -> Result<UdpTransport, RidError> {
+
+        let socket = UdpSocket::bind(local)?;
+
+        Ok(UdpTransport {
+            socket,
+            peer: None,
+        })
+    }
+
+    /// The address this transport is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr, RidError> {
+
+        Ok(self.socket.local_addr()?)
+
+    }
+}
+
+impl RidTransport for UdpTransport {
+
+    fn send(&mut self, buffer: &RIDReport) -> Result<(), RidError> {
+
+        let peer = self.peer.ok_or(RidError::Disconnected)?;
+
+        match self.socket.send_to(buffer, peer)? {
+            RID_PACKET_SIZE => Ok(()),
+            n => Err(RidError::ShortWrite(n)),
+        }
+
+    }
+
+    fn recv(&mut self, buffer: &mut RIDReport, timeout: Duration) -> Result<(), RidError> {
+
+        let deadline = Instant::now() + timeout;
+
+        self.socket.set_nonblocking(timeout.is_zero())?;
+
+        // One extra byte to catch oversized datagrams
+        let mut datagram = [0u8; RID_PACKET_SIZE + 1];
+
+        loop {
+
+            if !timeout.is_zero() {
+
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                if remaining.is_zero() {
+                    return Err(RidError::Timeout);
+                }
+
+                self.socket.set_read_timeout(Some(remaining))?;
+
+            }
+
+            // a bad datagram is dropped, the next one may be a report
+            if let (RID_PACKET_SIZE, peer) = self.socket.recv_from(&mut datagram)? {
+
+                buffer.copy_from_slice(&datagram[..RID_PACKET_SIZE]);
+                self.peer = Some(peer);
+
+                return Ok(());
+
+            }
+
+        }
+
+    }
+}
@@ -46,6 +46,15 @@ pub const RID_CYCLE_TIME_US: f64 = RID_CYCLE_TIME_S * 1E6;
 pub const RID_DEFAULT_VID: u16 = 0x1331;
 /// Defualt Dyse Indstries product id
 pub const RID_DEFAULT_PID: u16 = 0x0001;
+/// Version of the packet protocol, shared in the handshake.
+/// Bump this whenever the report layout changes.
+///
+/// 1. Hello handshake
+/// 2. Trigger packets
+/// 3. The checksum ([RID_CRC_INDEX]) and sequence number ([RID_SEQ_INDEX])
+///    come out of the task data, see [rtnt::MAX_TASK_DATA_BYTES]
+/// 4. Trigger times are sent as microseconds into the hour, see [rtnt::trigger]
+pub const RID_PROTOCOL_VERSION: u8 = 4;
 
 /////////////////////////////////////////////////////////////
 
@@ -53,8 +62,22 @@ pub const RID_DEFAULT_PID: u16 = 0x0001;
 /// currently does not have any implementations
 pub type RIDReport = [u8; RID_PACKET_SIZE];
 
-/// HID packet size, tried going bigger and things broke
+/// HID packet size, 64 bytes is a full speed HID report.
+///
+/// High speed HID and network transports can carry bigger reports,
+/// select one with the `packet-size-512` or `packet-size-1024` features.
+/// Both sides of a link must be built with the same size.
+#[cfg(not(any(feature = "packet-size-512", feature = "packet-size-1024")))]
 pub const RID_PACKET_SIZE: usize = 64;
+/// HID packet size, selected by the `packet-size-512` feature
+#[cfg(all(feature = "packet-size-512", not(feature = "packet-size-1024")))]
+pub const RID_PACKET_SIZE: usize = 512;
+/// HID packet size, selected by the `packet-size-1024` feature
+#[cfg(feature = "packet-size-1024")]
+pub const RID_PACKET_SIZE: usize = 1024;
+
+#[cfg(all(feature = "packet-size-512", feature = "packet-size-1024"))]
+compile_error!("packet-size-512 and packet-size-1024 are exclusive, enable one of them");
 
 /// Id of the task this packet is meant for
 pub const RID_TASK_INDEX: usize = 0;  
@@ -62,10 +85,20 @@ pub const RID_TASK_INDEX: usize = 0;
 pub const RID_MODE_INDEX: usize = 1; 
 /// Bytes reserved for PTP
 pub const RID_PTP_RESERVED_BYTES: usize = 16;
+/// Bytes reserved for the report sequence number
+pub const RID_SEQ_RESERVED_BYTES: usize = 2;
+/// Sequence number of the report, sits right before the PTP bytes
+pub const RID_SEQ_INDEX: usize = RID_PACKET_SIZE - RID_PTP_RESERVED_BYTES - RID_SEQ_RESERVED_BYTES;
+/// Bytes reserved for the report checksum
+pub const RID_CRC_RESERVED_BYTES: usize = 2;
+/// Checksum of the report, sits right before the sequence number
+pub const RID_CRC_INDEX: usize = RID_SEQ_INDEX - RID_CRC_RESERVED_BYTES;
 
 
 /////////////////////////////////////////////////////////////
 
+pub mod cobs;
+pub mod link;
 pub mod ptp;
 pub mod rtnt;
 
@@ -0,0 +1,162 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Link integrity
+//!
+//!   Every RTNT report carries a CRC-16/CCITT of its header and data,
+//! stored at [RID_CRC_INDEX]. The sequence number and PTP stamps are
+//! written when the report is sent, after it is sealed, so they are
+//! not covered.
+//!
+//!   Each direction numbers its reports at [RID_SEQ_INDEX]. A receiver
+//! feeds the numbers to [LinkStats] to count lost, duplicated and
+//! out of order reports.
+//!
+//! ```
+//! use rid::{RID_PACKET_SIZE, RID_MODE_INDEX, link::{seal, verify}};
+//!
+//! let mut report = [1u8; RID_PACKET_SIZE];
+//! seal(&mut report);
+//! assert!(verify(&report));
+//!
+//! report[RID_MODE_INDEX] ^= 0x04;
+//! assert!(!verify(&report));
+//! ```
+
+use crate::{RIDReport, RID_CRC_INDEX, RID_CRC_RESERVED_BYTES, RID_SEQ_INDEX, RID_SEQ_RESERVED_BYTES};
+
+/// CRC-16/CCITT polynomial
+pub const CRC16_POLY: u16 = 0x1021;
+/// CRC-16/CCITT initial value
+pub const CRC16_INIT: u16 = 0xFFFF;
+
+/// Calculate the CRC-16/CCITT of some bytes
+pub fn crc16(data: &[u8]) -> u16 {
+
+    let mut crc = CRC16_INIT;
+
+    for &byte in data {
+
+        crc ^= (byte as u16) << 8;
+
+        for _ in 0..8 {
+
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ CRC16_POLY,
+            };
+
+        }
+    }
+
+    crc
+
+}
+
+/// Write the checksum of a report's header and data
+pub fn seal(report: &mut RIDReport) {
+
+    let crc = crc16(&report[..RID_CRC_INDEX]);
+
+    report[RID_CRC_INDEX..RID_CRC_INDEX + RID_CRC_RESERVED_BYTES].copy_from_slice(&crc.to_be_bytes());
+
+}
+
+/// Check a report's header and data against its checksum
+pub fn verify(report: &RIDReport) -> bool {
+
+    let crc = u16::from_be_bytes([report[RID_CRC_INDEX], report[RID_CRC_INDEX + 1]]);
+
+    crc == crc16(&report[..RID_CRC_INDEX])
+
+}
+
+/// Write a sequence number to a report
+pub fn write_sequence(report: &mut RIDReport, sequence: u16) {
+
+    report[RID_SEQ_INDEX..RID_SEQ_INDEX + RID_SEQ_RESERVED_BYTES].copy_from_slice(&sequence.to_be_bytes());
+
+}
+
+/// Read the sequence number of a report
+pub fn read_sequence(report: &RIDReport) -> u16 {
+
+    u16::from_be_bytes([report[RID_SEQ_INDEX], report[RID_SEQ_INDEX + 1]])
+
+}
+
+/// Packet loss statistics for one direction of a link
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct LinkStats {
+    /// Number of reports received
+    pub received: usize,
+    /// Number of reports that never arrived
+    pub lost: usize,
+    /// Number of reports that arrived more than once
+    pub duplicated: usize,
+    /// Number of reports that arrived after a newer report
+    pub out_of_order: usize,
+    /// Newest sequence number received
+    last: Option<u16>,
+}
+
+impl LinkStats {
+
+    /// Update the statistics with a received sequence number.
+    ///
+    /// Gaps count as lost. If a missing report shows up 
+    /// later it moves from lost to out of order. Sequence
+    /// numbers wrap, distances are taken modulo 2^16.
+    pub fn track(&mut self, sequence: u16) {
+
+        self.received += 1;
+
+        let last = match self.last {
+            Some(last) => last,
+            None => {
+
+                self.last = Some(sequence);
+                return;
+
+            },
+        };
+
+        let distance = sequence.wrapping_sub(last) as i16;
+
+        match distance {
+            0 => self.duplicated += 1,
+            d if d > 0 => {
+
+                self.lost += d as usize - 1;
+                self.last = Some(sequence);
+
+            },
+            _ => {
+
+                self.out_of_order += 1;
+                self.lost = self.lost.saturating_sub(1);
+
+            },
+        }
+
+    }
+
+    /// Forget all statistics
+    pub fn reset(&mut self) {
+
+        *self = LinkStats::default();
+
+    }
+}
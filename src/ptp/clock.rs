@@ -0,0 +1,198 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Client Clock
+//!
+//!   The client half of the estimate. Every report from the host carries the
+//! host's read time of the client's last reply and the host's write time of the
+//! report. With the client's own write and read times that is a full exchange,
+//! seen from the client:
+//!
+//! client write (t3) -> host read (t4) -> host write (t1) -> client read (t2)
+//!
+//! [ClientClock] runs those exchanges through a [DelayFilter] and a [SkewEstimator]
+//! so firmware can label its data with host times.
+//!
+//! ```
+//! use rid::ptp::{Micros, TimeStamp, clock::{ClientClock, ClockSource}};
+//!
+//! // the firmware's counter
+//! struct Counter(u32);
+//!
+//! impl ClockSource for Counter {
+//!     fn micros(&self) -> u32 { self.0 }
+//! }
+//!
+//! let mut clock = ClientClock::new(Counter(5_000));
+//!
+//! // host runs 900ms ahead, 10us flights: client wrote at 1_000, host
+//! // read at 901_010 and wrote at 901_020, client read at 1_030.
+//! // In firmware this is ptp_stamp.client_read(&buffer, clock.micros())
+//! let stamp = TimeStamp::new(1_030, 1_000, 901_010, 901_020);
+//!
+//! assert!(clock.sample(&stamp));
+//! assert_eq!(clock.host_now(), Micros::new(905_000));
+//! ```
+
+use crate::ptp::{
+    Duration, Micros, TimeStamp, ptp_delay, ptp_offset,
+    estimator::{LinearFit, SkewEstimator},
+    filter::DelayFilter,
+};
+
+/// A free running microsecond counter, what the client stamps reports with
+pub trait ClockSource {
+    /// Microseconds into the current hour, see [TimeStamp]
+    fn micros(&self) -> u32;
+}
+
+/// Estimates host time on the client
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ClientClock<C: ClockSource> {
+    /// The client's counter
+    pub source: C,
+    /// Drops exchanges with a long round trip
+    pub delay_filter: DelayFilter,
+    /// Least squares fit of the client clock, C(t) = m * H(t) + b
+    pub estimator: SkewEstimator,
+
+    client: Duration,
+    host: Duration,
+}
+
+impl<C: ClockSource> ClientClock<C> {
+    /// Create a clock that reads the source, nothing is
+    /// known about the host until the first [ClientClock::sample()]
+    pub fn new(source: C) -> ClientClock<C> {
+
+        ClientClock {
+            source,
+            delay_filter: DelayFilter::default(),
+            estimator: SkewEstimator::default(),
+
+            client: Duration::default(),
+            host: Duration::default(),
+        }
+
+    }
+
+    /// Forget the host, call this when the link is lost
+    pub fn reset(&mut self) {
+
+        self.delay_filter.reset();
+        self.estimator.reset();
+        self.host = Duration::default();
+
+    }
+
+    /// Read the source
+    pub fn micros(&self) -> u32 {
+
+        self.source.micros()
+
+    }
+
+    /// The full client time of a client stamp, taken
+    /// less than half an hour from the last one
+    pub fn client_time(&mut self, stamp: u32) -> Micros {
+
+        self.client.advance(stamp)
+
+    }
+
+    /// Add the exchange that ended at the stamp's client read.
+    ///
+    /// Call after [TimeStamp::client_read()]. Returns false if there was
+    /// no exchange yet (nothing written or nothing echoed) or if the
+    /// [DelayFilter] rejected it.
+    pub fn sample(&mut self, stamp: &TimeStamp) -> bool {
+
+        if stamp[1] == 0 || stamp[2] == 0 {
+            return false;
+        }
+
+        let client_read = self.client_time(stamp[0]);
+        let client_write = client_read.unwrap(stamp[1]);
+
+        let host_write = self.host.advance(stamp[3]);
+        let host_read = host_write.unwrap(stamp[2]);
+
+        // the client writes first, so it plays t1 in the delay
+        let delay = ptp_delay(client_write.micros() as i64,
+            host_read.micros() as i64,
+            host_write.micros() as i64,
+            client_read.micros() as i64,
+        );
+
+        if !self.delay_filter.filter(delay) {
+            return false;
+        }
+
+        let offset = ptp_offset(host_write.micros() as i64,
+            client_read.micros() as i64,
+            client_write.micros() as i64,
+            host_read.micros() as i64,
+        );
+
+        self.estimator.update(host_read + ((host_write - host_read) / 2), offset);
+
+        true
+
+    }
+
+    /// The latest fit of the client clock
+    pub fn fit(&self) -> LinearFit {
+
+        self.estimator.fit()
+
+    }
+
+    /// True once an exchange passed the filter
+    pub fn is_synced(&self) -> bool {
+
+        self.estimator.fit().points > 0
+
+    }
+
+    /// Convert a client stamp to a host time, for stamps taken
+    /// less than half an hour from the last one
+    pub fn to_host(&self, client: u32) -> Micros {
+
+        self.estimator.fit().to_host(self.client.unwrap(client).into())
+
+    }
+
+    /// Convert a host time to a client stamp
+    pub fn to_client(&self, host: Micros) -> u32 {
+
+        self.estimator.fit().to_client(host).wrapped()
+
+    }
+
+    /// The current host time
+    pub fn host_now(&self) -> Micros {
+
+        self.to_host(self.micros())
+
+    }
+
+    /// Standard deviation (microseconds) of [ClientClock::host_now()]
+    pub fn uncertainty(&self) -> f64 {
+
+        let fit = self.estimator.fit();
+
+        fit.uncertainty(self.host_now())
+
+    }
+}
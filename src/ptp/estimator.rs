@@ -0,0 +1,347 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Skew Estimator
+//!
+//!   Sliding window least squares fit of the client clock against the host clock:
+//!
+//! C(t) = m * H(t) + b
+//!
+//! The fit is done on the offset, o(t) = C(t) - H(t) = (m - 1) * H(t) + b, which
+//! keeps the numbers small. Samples are averaged into buckets [SkewEstimator::spacing]
+//! apart so the fixed window of [SKEW_WINDOW] points covers seconds instead of cycles.
+//! Every fit comes with the standard deviations of its coefficients, [LinearFit::uncertainty()]
+//! gives the confidence of a conversion at any time.
+//!
+//! no_std, nothing is allocated.
+//!
+//! ```
+//! use rid::ptp::{Micros, estimator::SkewEstimator};
+//!
+//! let mut estimator = SkewEstimator::default();
+//!
+//! // client runs 2s ahead and 50ppm slow, sampled every ms
+//! for i in 0..3_000u64 {
+//!
+//!     let host = Micros::new(i * 1_000);
+//!     estimator.update(host, 2_000_000 - (i as i64 / 20));
+//!
+//! }
+//!
+//! let fit = estimator.fit();
+//!
+//! assert!((fit.skew() - 0.99995).abs() < 1E-6);
+//! assert!((fit.to_client(Micros::new(4_000_000)) - Micros::new(5_999_800)).abs() <= 1);
+//! ```
+
+use crate::ptp::{Micros, ptp_offset, round, sqrt};
+
+/// Number of points a [SkewEstimator] fits
+pub const SKEW_WINDOW: usize = 256;
+/// Default host time between the points of a [SkewEstimator] (microseconds)
+pub const SKEW_DEFAULT_SPACING: u64 = 10_000;
+
+/// C(t) = m * H(t) + b, fit by a [SkewEstimator]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct LinearFit {
+    /// Host time the fit is centered on, the uncertainty is smallest here
+    pub reference: Micros,
+    /// Offset at the reference (microseconds)
+    pub offset: f64,
+    /// m - 1, the rate error of the client
+    pub drift: f64,
+    /// Standard deviation of the offset at the reference (microseconds)
+    pub offset_std: f64,
+    /// Standard deviation of the drift
+    pub drift_std: f64,
+    /// Number of points in the fit
+    pub points: usize,
+}
+
+impl Default for LinearFit {
+    /// Identical clocks, infinitely uncertain
+    fn default() -> LinearFit {
+
+        LinearFit {
+            reference: Micros::ZERO,
+            offset: 0.0,
+            drift: 0.0,
+            offset_std: f64::INFINITY,
+            drift_std: f64::INFINITY,
+            points: 0,
+        }
+
+    }
+}
+
+impl LinearFit {
+    /// m in C(t) = m * H(t) + b
+    pub fn skew(&self) -> f64 {
+
+        1.0 + self.drift
+
+    }
+
+    /// The offset (microseconds) at a host time, o(t) = C(t) - H(t)
+    pub fn offset_at(&self, host: Micros) -> i64 {
+
+        round(self.offset + (self.drift * (host - self.reference) as f64))
+
+    }
+
+    /// Convert a host time to a client time
+    pub fn to_client(&self, host: Micros) -> Micros {
+
+        host + self.offset_at(host)
+
+    }
+
+    /// Convert a client time to a host time
+    pub fn to_host(&self, client: Micros) -> Micros {
+
+        // H = (C - b) / m, solved around the reference
+        let client_reference = self.reference.micros() as f64 + self.offset;
+        let host = self.reference.micros() as f64 + ((client.micros() as f64 - client_reference) / self.skew());
+
+        Micros::new(round(host).max(0) as u64)
+
+    }
+
+    /// Standard deviation (microseconds) of a conversion at a host time.
+    ///
+    /// Grows with the distance from [LinearFit::reference], extrapolating
+    /// far past the window costs confidence.
+    pub fn uncertainty(&self, host: Micros) -> f64 {
+
+        let dt = (host - self.reference) as f64;
+
+        sqrt((self.offset_std * self.offset_std) + (self.drift_std * self.drift_std * dt * dt))
+
+    }
+
+    /// Client times `sigmas` standard deviations below and
+    /// above the conversion of a host time
+    pub fn to_client_bounds(&self, host: Micros, sigmas: f64) -> (Micros, Micros) {
+
+        let bound = round(sigmas * self.uncertainty(host));
+        let client = self.to_client(host);
+
+        (client + -bound, client + bound)
+
+    }
+}
+
+/// Average of the samples that land between two points
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+struct Bucket {
+    start: Micros,
+    host_sum: i64,
+    offset_sum: i64,
+    count: i64,
+}
+
+/// Fixed capacity sliding window least squares fit of the client clock
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct SkewEstimator {
+    /// Host time between points (microseconds), samples in between are averaged
+    pub spacing: u64,
+
+    hosts: [Micros; SKEW_WINDOW],
+    offsets: [i64; SKEW_WINDOW],
+    head: usize,
+    len: usize,
+
+    bucket: Bucket,
+    fit: LinearFit,
+}
+
+impl Default for SkewEstimator {
+    /// An estimator with [SKEW_DEFAULT_SPACING]
+    fn default() -> SkewEstimator {
+
+        SkewEstimator::new(SKEW_DEFAULT_SPACING)
+
+    }
+}
+
+impl SkewEstimator {
+    /// Create an empty estimator, points are `spacing` microseconds apart
+    pub fn new(spacing: u64) -> SkewEstimator {
+
+        SkewEstimator {
+            spacing,
+
+            hosts: [Micros::ZERO; SKEW_WINDOW],
+            offsets: [0; SKEW_WINDOW],
+            head: 0,
+            len: 0,
+
+            bucket: Bucket::default(),
+            fit: LinearFit::default(),
+        }
+
+    }
+
+    /// Forget every sample
+    pub fn reset(&mut self) {
+
+        *self = SkewEstimator::new(self.spacing);
+
+    }
+
+    /// Add a PTP exchange, see [ptp_offset()] for the event order.
+    /// The sample is taken at the host midpoint of the exchange.
+    pub fn sample(&mut self, host_write: Micros, client_read: Micros, client_write: Micros, host_read: Micros) {
+
+        let offset = ptp_offset(host_write.micros() as i64,
+            client_read.micros() as i64,
+            client_write.micros() as i64,
+            host_read.micros() as i64,
+        );
+
+        self.update(host_write + ((host_read - host_write) / 2), offset);
+
+    }
+
+    /// Add a measured offset (microseconds) at a host time.
+    ///
+    /// The fit is updated when a bucket of samples closes,
+    /// the first sample is fit right away.
+    pub fn update(&mut self, host: Micros, offset: i64) {
+
+        if self.bucket.count > 0 && host - self.bucket.start >= self.spacing as i64 {
+
+            self.close_bucket();
+
+        }
+
+        if self.bucket.count == 0 {
+
+            self.bucket.start = host;
+
+        }
+
+        self.bucket.host_sum += host - self.bucket.start;
+        self.bucket.offset_sum += offset;
+        self.bucket.count += 1;
+
+        if self.len == 0 {
+
+            self.fit = LinearFit { reference: host, offset: offset as f64, points: 1, ..LinearFit::default() };
+
+        }
+
+    }
+
+    /// The latest fit
+    pub fn fit(&self) -> LinearFit {
+
+        self.fit
+
+    }
+
+    /// Number of points in the window
+    pub fn len(&self) -> usize {
+
+        self.len
+
+    }
+
+    /// True before the first bucket closes
+    pub fn is_empty(&self) -> bool {
+
+        self.len == 0
+
+    }
+
+    /// Push the bucket's average as a point and refit
+    fn close_bucket(&mut self) {
+
+        let count = self.bucket.count;
+        let host = self.bucket.start + (self.bucket.host_sum / count);
+        let offset = round(self.bucket.offset_sum as f64 / count as f64);
+
+        self.hosts[self.head] = host;
+        self.offsets[self.head] = offset;
+
+        self.head = (self.head + 1) % SKEW_WINDOW;
+        self.len = (self.len + 1).min(SKEW_WINDOW);
+
+        self.bucket = Bucket::default();
+
+        self.refit();
+
+    }
+
+    /// Least squares over the window, relative to the oldest point
+    fn refit(&mut self) {
+
+        let n = self.len;
+        let oldest = (self.head + SKEW_WINDOW - n) % SKEW_WINDOW;
+
+        let host_0 = self.hosts[oldest];
+        let offset_0 = self.offsets[oldest];
+
+        let point = |i: usize| {
+            let j = (oldest + i) % SKEW_WINDOW;
+            ((self.hosts[j] - host_0) as f64, (self.offsets[j] - offset_0) as f64)
+        };
+
+        let (x_sum, y_sum) = (0..n).map(point).fold((0.0, 0.0), |(xs, ys), (x, y)| (xs + x, ys + y));
+        let x_mean = x_sum / n as f64;
+        let y_mean = y_sum / n as f64;
+
+        let (sxx, sxy) = (0..n).map(point).fold((0.0, 0.0), |(sxx, sxy), (x, y)| {
+            (sxx + ((x - x_mean) * (x - x_mean)), sxy + ((x - x_mean) * (y - y_mean)))
+        });
+
+        let drift = match sxx > 0.0 {
+            true => sxy / sxx,
+            false => 0.0,
+        };
+
+        let residuals = (0..n).map(point).fold(0.0, |sum, (x, y)| {
+            let r = y - y_mean - (drift * (x - x_mean));
+            sum + (r * r)
+        });
+
+        let (offset_std, drift_std) = match n > 2 && sxx > 0.0 {
+            true => {
+
+                let variance = residuals / (n - 2) as f64;
+
+                (sqrt(variance / n as f64), sqrt(variance / sxx))
+
+            },
+            false => (f64::INFINITY, f64::INFINITY),
+        };
+
+        // center on a whole microsecond, move the offset with it
+        let reference = host_0 + round(x_mean);
+        let offset = offset_0 as f64 + y_mean + (drift * (round(x_mean) as f64 - x_mean));
+
+        self.fit = LinearFit {
+            reference,
+            offset,
+            drift,
+            offset_std,
+            drift_std,
+            points: n,
+        };
+
+    }
+}
@@ -0,0 +1,151 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Round Trip Delay Filter
+//!
+//!   USB polling holds a report for a frame now and then, in one direction.
+//! [crate::ptp::ptp_offset()] splits the round trip evenly so that sample's offset
+//! is off by half the hold. Fast exchanges can't have been held long, the
+//! [DelayFilter] only passes samples with a round trip ([crate::ptp::ptp_delay()])
+//! close to the smallest one seen recently.
+//!
+//! The minimum is taken over the last [DELAY_WINDOW] samples (passed or not)
+//! so the filter follows the link when its delay changes.
+//!
+//! ```
+//! use rid::ptp::filter::DelayFilter;
+//!
+//! let mut filter = DelayFilter::new(20);
+//!
+//! assert!(filter.filter(100));
+//! assert!(filter.filter(110));
+//! assert!(!filter.filter(1_100));    // held for a frame
+//! assert!(filter.filter(90));
+//!
+//! assert_eq!(filter.rejected(), 1);
+//! ```
+
+use crate::ptp::{Micros, ptp_delay};
+
+/// Number of recent round trips the minimum is taken over
+pub const DELAY_WINDOW: usize = 64;
+/// Default slack over the minimum round trip (microseconds)
+pub const DELAY_DEFAULT_TOLERANCE: i64 = 50;
+
+/// Passes PTP samples with a round trip near the recent minimum
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct DelayFilter {
+    /// Samples slower than the minimum by more than this are rejected (microseconds)
+    pub tolerance: i64,
+
+    delays: [i64; DELAY_WINDOW],
+    head: usize,
+    len: usize,
+
+    accepted: usize,
+    rejected: usize,
+}
+
+impl Default for DelayFilter {
+    /// A filter with [DELAY_DEFAULT_TOLERANCE]
+    fn default() -> DelayFilter {
+
+        DelayFilter::new(DELAY_DEFAULT_TOLERANCE)
+
+    }
+}
+
+impl DelayFilter {
+    /// Create an empty filter
+    pub fn new(tolerance: i64) -> DelayFilter {
+
+        DelayFilter {
+            tolerance,
+
+            delays: [0; DELAY_WINDOW],
+            head: 0,
+            len: 0,
+
+            accepted: 0,
+            rejected: 0,
+        }
+
+    }
+
+    /// Forget every sample
+    pub fn reset(&mut self) {
+
+        *self = DelayFilter::new(self.tolerance);
+
+    }
+
+    /// Check a PTP exchange, see [crate::ptp::ptp_offset()] for the event order.
+    /// Returns false if the exchange should not be used.
+    pub fn check(&mut self, host_write: Micros, client_read: Micros, client_write: Micros, host_read: Micros) -> bool {
+
+        self.filter(ptp_delay(host_write.micros() as i64,
+            client_read.micros() as i64,
+            client_write.micros() as i64,
+            host_read.micros() as i64,
+        ))
+
+    }
+
+    /// Record a round trip delay (microseconds).
+    /// Returns false if it is too far over the recent minimum.
+    pub fn filter(&mut self, delay: i64) -> bool {
+
+        self.delays[self.head] = delay;
+        self.head = (self.head + 1) % DELAY_WINDOW;
+        self.len = (self.len + 1).min(DELAY_WINDOW);
+
+        match delay <= self.min_delay() + self.tolerance {
+            true => {
+
+                self.accepted += 1;
+                true
+
+            },
+            false => {
+
+                self.rejected += 1;
+                false
+
+            },
+        }
+
+    }
+
+    /// Smallest round trip in the window (microseconds), 0 if empty
+    pub fn min_delay(&self) -> i64 {
+
+        self.delays[..self.len].iter().copied().min().unwrap_or(0)
+
+    }
+
+    /// Number of samples passed since the last reset
+    pub fn accepted(&self) -> usize {
+
+        self.accepted
+
+    }
+
+    /// Number of samples rejected since the last reset
+    pub fn rejected(&self) -> usize {
+
+        self.rejected
+
+    }
+}
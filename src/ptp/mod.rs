@@ -0,0 +1,524 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Precision Timing Protocol
+//!
+//!   This crate can be included in a firmware build (use the client calls) 
+//! or built using the "std" feature.
+//!
+
+use crate::{RIDReport, RID_PACKET_SIZE, RID_PTP_RESERVED_BYTES};
+
+use core::ops::{Add, Index, Sub};
+
+/// Client read time index, client sets and host reads this
+pub const PTP_CRTS_INDEX: usize = RID_PACKET_SIZE - RID_PTP_RESERVED_BYTES;
+/// Client write time index, client sets and host reads this
+pub const PTP_CWTS_INDEX: usize = PTP_CRTS_INDEX + 4;
+/// Host read time index, host sets and client reads this
+pub const PTP_HRTS_INDEX: usize = PTP_CWTS_INDEX + 4;
+/// Client write time index, host sets and client reads this
+pub const PTP_HWTS_INDEX: usize = PTP_HRTS_INDEX + 4;
+
+/// Second to Hour constant: seconds = hours * SEC_PER_HOUR
+pub const SEC_PER_HOUR: u64 = 3_600;
+/// Microsecond to Second constant: microseconds = seconds * USEC_PER_SEC
+pub const USEC_PER_SEC: u32 = 1_000_000;
+/// Microsecond to Hour constant, [TimeStamp] fields roll over at this period
+pub const USEC_PER_HOUR: u64 = USEC_PER_SEC as u64 * SEC_PER_HOUR;
+
+
+/// # Calculates the PTP offset
+/// 
+/// Assumes events go: host write (t1) -> client read (t2) -> client write (t3) -> host read (t4)
+/// 
+/// C(t) = H(t) + o(t)
+///
+/// This equation can be rearranged to calculate more accurately at different events
+/// 
+/// o(t) + d = (t2 - t1), -o(t) + d = (t4 - t3)
+/// 
+/// 2 * o = (t2 - t1) - (t4 + t3) - d + d
+/// 
+/// o(t) = (t2 + t3 - t1 - t4) / 2
+/// 
+/// Times are exact microseconds (see [Micros]), the result
+/// only loses the half microsecond of the division.
+///
+/// ```
+///
+/// // Done on host, after host read event
+///
+/// let client_read     =  1;    // client read, then wrote
+/// let client_write    =  2;
+///
+/// let host_read       = 10;    // host read, but has not written
+/// let host_write      =  9;
+///
+/// let offset = rid::ptp::ptp_offset(host_write, client_read, client_write, host_read);
+///
+/// assert_eq!(client_read, host_write + offset);
+///
+/// ```
+///
+/// ```
+/// // Done on client, after client read event
+/// let client_read     =  3;    // client read, but hasn't written
+/// let client_write    =  2;
+///
+/// let host_read       = 10;    // host read, then wrote
+/// let host_write      = 11;
+///
+/// let offset = rid::ptp::ptp_offset(host_write, client_read, client_write, host_read);
+///
+/// assert_eq!(client_write - offset, host_read);
+/// ```
+
+pub fn ptp_offset(t1: i64, t2: i64, t3: i64, t4: i64) -> i64 {
+    ((t2 - t1) + (t3 - t4)) / 2
+}
+
+/// # Calculates the PTP round trip delay
+///
+/// Same event order as [ptp_offset()], the time the exchange
+/// spent in flight (both directions), without the time the client held it.
+///
+/// d = (t4 - t1) - (t3 - t2)
+///
+/// [ptp_offset()] assumes each direction took d / 2, a sample with a
+/// long delay is likely asymmetric and its offset is off by up to d / 2.
+///
+/// ```
+/// // 30us out, 10us on the client, 50us back
+/// let delay = rid::ptp::ptp_delay(0, 1_030, 1_040, 90);
+///
+/// assert_eq!(delay, 80);
+/// ```
+pub fn ptp_delay(t1: i64, t2: i64, t3: i64, t4: i64) -> i64 {
+    (t4 - t1) - (t3 - t2)
+}
+
+/// # Exact timestamp
+/// Microseconds since an epoch in a u64, this never rolls over
+/// and never loses precision like f32 microseconds do after ~16 seconds.
+///
+/// Reports only carry the microseconds into the current hour
+/// ([Micros::wrapped()]), [Micros::unwrap()] recovers the full time from a
+/// nearby reference time.
+///
+/// ```
+/// use rid::ptp::{Micros, USEC_PER_HOUR};
+///
+/// let before = Micros::new(USEC_PER_HOUR - 10);   // just before the rollover
+/// let after = Micros::new(USEC_PER_HOUR + 10);
+///
+/// assert_eq!(after.wrapped(), 10);
+/// assert_eq!(before.unwrap(after.wrapped()), after);
+/// assert_eq!(after - before, 20);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Micros(pub u64);
+
+impl Micros {
+    /// The epoch
+    pub const ZERO: Micros = Micros(0);
+
+    /// Create a timestamp
+    pub fn new(micros: u64) -> Micros {
+
+        Micros(micros)
+
+    }
+
+    /// read the microseconds
+    pub fn micros(&self) -> u64 {
+
+        self.0
+
+    }
+
+    /// The microseconds into the current hour, what a [TimeStamp] carries
+    pub fn wrapped(&self) -> u32 {
+
+        (self.0 % USEC_PER_HOUR) as u32
+
+    }
+
+    /// The full time of a wrapped stamp that is closest to Self, see [Duration::unwrap()].
+    ///
+    /// Correct as long as the stamp was taken less than
+    /// half an hour before or after Self.
+    pub fn unwrap(&self, stamp: u32) -> Micros {
+
+        Duration::from(*self).unwrap(stamp).into()
+
+    }
+
+    /// read the timestamp in seconds, only for display
+    pub fn as_secs_f64(&self) -> f64 {
+
+        self.0 as f64 / USEC_PER_SEC as f64
+
+    }
+}
+
+impl Sub for Micros {
+    type Output = i64;
+
+    /// Signed microseconds between two timestamps
+    fn sub(self, rhs: Micros) -> i64 {
+
+        self.0 as i64 - rhs.0 as i64
+
+    }
+}
+
+impl Add<i64> for Micros {
+    type Output = Micros;
+
+    /// Offset a timestamp, saturates at the epoch
+    fn add(self, rhs: i64) -> Micros {
+
+        Micros(self.0.saturating_add_signed(rhs))
+
+    }
+}
+
+#[derive(Debug)]
+/// # Precision Timing Protocol implementation
+/// This is used by a host and client to share clock measurments based 
+/// on message passing events. The algorithm uses these events to estimate
+/// an offset between the host and client system times. This offset can
+/// be used to syncronize messages across machines. This will allow better
+/// data association and task scheduling.
+pub struct TimeStamp {
+    client_read: u32,
+    client_write: u32,
+    host_read: u32,
+    host_write: u32,
+}
+
+impl TimeStamp {
+    /// Create and return a [TimeStamp] with the given event times
+    pub fn new(client_read: u32, client_write: u32, host_read: u32, host_write: u32) -> TimeStamp {
+        TimeStamp {
+            client_read: client_read,
+            client_write: client_write,
+            host_read: host_read,
+            host_write: host_write,
+        }
+    }
+
+    /// Calculate the PTP offset with the stamps of each system.
+    ///
+    /// The stamps roll over every hour, this is only valid when 
+    /// both clocks are in the same hour. Use full [Micros] times otherwise.
+    pub fn offset(&self) -> i64 {
+
+        ptp_offset(self.host_write as i64, 
+            self.client_read as i64, 
+            self.client_write as i64, 
+            self.host_read as i64
+        )
+    
+    }
+
+    /// Write the microsecond event times to a report
+    pub fn stamp(&mut self, buffer: &mut RIDReport) {
+        buffer[PTP_CRTS_INDEX..PTP_CRTS_INDEX + 4].copy_from_slice(&self.client_read.to_be_bytes());
+        buffer[PTP_CWTS_INDEX..PTP_CWTS_INDEX + 4]
+            .copy_from_slice(&self.client_write.to_be_bytes());
+        buffer[PTP_HRTS_INDEX..PTP_HRTS_INDEX + 4].copy_from_slice(&self.host_read.to_be_bytes());
+        buffer[PTP_HWTS_INDEX..PTP_HWTS_INDEX + 4].copy_from_slice(&self.host_write.to_be_bytes());
+    }
+
+    /// Read the client event stamps from a report
+    pub fn read_client_stamp(&self, buffer: &RIDReport) -> (u32, u32) {
+        (
+            u32::from_be_bytes([
+                buffer[PTP_CRTS_INDEX],
+                buffer[PTP_CRTS_INDEX + 1],
+                buffer[PTP_CRTS_INDEX + 2],
+                buffer[PTP_CRTS_INDEX + 3],
+            ]),
+            u32::from_be_bytes([
+                buffer[PTP_CWTS_INDEX],
+                buffer[PTP_CWTS_INDEX + 1],
+                buffer[PTP_CWTS_INDEX + 2],
+                buffer[PTP_CWTS_INDEX + 3],
+            ])
+        )
+    }
+
+    /// Read the host event stamps from a report
+    pub fn read_host_stamp(&self, buffer: &RIDReport) -> (u32, u32) {
+        (
+            u32::from_be_bytes([
+                buffer[PTP_HRTS_INDEX],
+                buffer[PTP_HRTS_INDEX + 1],
+                buffer[PTP_HRTS_INDEX + 2],
+                buffer[PTP_HRTS_INDEX + 3],
+            ]),
+            u32::from_be_bytes([
+                buffer[PTP_HWTS_INDEX],
+                buffer[PTP_HWTS_INDEX + 1],
+                buffer[PTP_HWTS_INDEX + 2],
+                buffer[PTP_HWTS_INDEX + 3],
+            ])
+        )
+    }
+
+    /// update the host read time and save the clients event stamps
+    pub fn host_read(&mut self, buffer: &RIDReport, timestamp: u32) {
+        self.host_read = timestamp;
+
+        (self.client_read, self.client_write) = self.read_client_stamp(buffer);
+    }
+
+    /// update the client read time and save the hosts event stamps
+    pub fn client_read(&mut self, buffer: &RIDReport, timestamp: u32) {
+        self.client_read = timestamp;
+
+        (self.host_read, self.host_write) = self.read_host_stamp(buffer);
+
+    }
+
+    /// update the host write time and stamp a buffer
+    pub fn host_stamp(&mut self, buffer: &mut RIDReport, timestamp: u32) {
+
+        self.host_write = timestamp;
+        self.stamp(buffer);
+    
+    }
+
+    /// update the client write time and stamp a buffer
+    pub fn client_stamp(&mut self, buffer: &mut RIDReport, timestamp: u32) {
+
+        self.client_write = timestamp;
+        self.stamp(buffer);
+
+    }
+}
+
+impl Index<usize> for TimeStamp {
+    type Output = u32;
+
+    fn index(&self, idx: usize) -> &Self::Output {
+        match idx {
+            0 => &self.client_read,
+            1 => &self.client_write,
+            2 => &self.host_read,
+            _ => &self.host_write,
+        }
+    }
+}
+
+/// # Hour counting timer
+/// The microseconds into the current hour (what a [TimeStamp] carries)
+/// and the number of hours since the epoch.
+///
+/// Every rollover is counted here, [Duration::advance()] follows a stream
+/// of wrapped stamps, so differences and comparisons hold across the hour.
+///
+/// ```
+/// use rid::ptp::{Duration, USEC_PER_HOUR};
+///
+/// let mut timer = Duration::new(USEC_PER_HOUR as u32 - 10);
+/// let start = timer;
+///
+/// timer.advance(5);    // the counter rolled over
+///
+/// assert_eq!(timer.epoch(), 1);
+/// assert_eq!(timer.micros(), 5);
+/// assert_eq!(timer - start, 15);
+/// assert!(timer > start);
+/// assert_eq!(timer.elapsed_since(start), 15);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Duration {
+    // field order makes the derived ordering compare hours first
+    epoch: u32,
+    microseconds: u32,
+}
+
+impl Duration {
+
+    /// Create a new duration in the first hour,
+    /// larger values roll into the next hours
+    pub fn new(micros: u32) -> Duration {
+
+        Duration::from(Micros::new(micros as u64))
+
+    }
+
+    /// add microseconds to the timer, counts the rollovers.
+    /// Returns the microseconds into the current hour
+    pub fn add_micros(&mut self, micros: u32) -> u32 {
+
+        *self = Duration::from(Micros::from(*self) + micros as i64);
+
+        self.microseconds
+    }
+
+    /// The time of a wrapped stamp closest to Self, less than half an hour
+    /// before or after it. Never goes before the epoch.
+    pub fn unwrap(&self, stamp: u32) -> Duration {
+
+        let microseconds = (stamp as u64 % USEC_PER_HOUR) as u32;
+        let half_hour = (USEC_PER_HOUR / 2) as u32;
+
+        let epoch = match microseconds.cmp(&self.microseconds) {
+            core::cmp::Ordering::Greater if microseconds - self.microseconds > half_hour => self.epoch.saturating_sub(1),
+            core::cmp::Ordering::Less if self.microseconds - microseconds > half_hour => self.epoch + 1,
+            _ => self.epoch,
+        };
+
+        Duration { epoch, microseconds }
+
+    }
+
+    /// Move to a wrapped stamp (see [Duration::unwrap()]), call this
+    /// with every reading of a free running hourly counter.
+    /// Returns the full time of the stamp
+    pub fn advance(&mut self, stamp: u32) -> Micros {
+
+        *self = self.unwrap(stamp);
+
+        Micros::from(*self)
+
+    }
+
+    /// Microseconds from an earlier time to Self, 0 if it is not earlier
+    pub fn elapsed_since(&self, earlier: Duration) -> u64 {
+
+        (*self - earlier).max(0) as u64
+
+    }
+
+    /// read the number of hours since the epoch
+    pub fn epoch(&self) -> u32 {
+
+        self.epoch
+
+    }
+
+    /// read the microseconds field (into the current hour)
+    pub fn micros(&self) -> u32 {
+
+        self.microseconds
+    
+    }
+
+    /// read the microseconds field as milliseconds
+    pub fn millis(&self) -> u32 {
+
+        self.microseconds / 1_000
+
+    }
+
+    /// read the timer value in seconds as a floating point,
+    /// hours included
+    pub fn time(&self) -> f32 {
+
+        Micros::from(*self).as_secs_f64() as f32
+    
+    }
+}
+
+impl From<Micros> for Duration {
+    /// Split a full time into hours and microseconds
+    fn from(time: Micros) -> Duration {
+
+        Duration {
+            epoch: (time.micros() / USEC_PER_HOUR) as u32,
+            microseconds: time.wrapped(),
+        }
+
+    }
+}
+
+impl From<Duration> for Micros {
+    /// Join the hours and microseconds
+    fn from(time: Duration) -> Micros {
+
+        Micros::new((time.epoch as u64 * USEC_PER_HOUR) + time.microseconds as u64)
+
+    }
+}
+
+impl Sub for Duration {
+    type Output = i64;
+
+    /// Signed microseconds between two times, rollovers included
+    fn sub(self, rhs: Duration) -> i64 {
+
+        Micros::from(self) - Micros::from(rhs)
+
+    }
+}
+
+/// Square root for no_std builds (core has no f64::sqrt).
+///
+/// Newton's method from an exponent halving guess,
+/// returns 0 for negative inputs.
+///
+/// ```
+/// assert!((rid::ptp::sqrt(2.0) - 1.414213562373095).abs() < 1E-12);
+/// assert_eq!(rid::ptp::sqrt(0.0), 0.0);
+/// ```
+pub fn sqrt(x: f64) -> f64 {
+
+    if x <= 0.0 || x.is_nan() {
+        return 0.0;
+    }
+
+    if x.is_infinite() {
+        return x;
+    }
+
+    // halving the exponent bits lands within a factor of 2
+    let mut y = f64::from_bits((x.to_bits() >> 1) + (1023u64 << 51));
+
+    for _ in 0..6 {
+        y = 0.5 * (y + (x / y));
+    }
+
+    y
+
+}
+
+/// Round to the nearest microsecond for no_std builds
+/// (core has no f64::round), halves round away from 0.
+///
+/// ```
+/// assert_eq!(rid::ptp::round(2.5), 3);
+/// assert_eq!(rid::ptp::round(-2.5), -3);
+/// ```
+pub fn round(x: f64) -> i64 {
+
+    match x >= 0.0 {
+        true => (x + 0.5) as i64,
+        false => (x - 0.5) as i64,
+    }
+
+}
+
+pub mod clock;
+pub mod estimator;
+pub mod filter;
+pub mod servo;
+pub mod stats;
+pub mod sync;
@@ -0,0 +1,252 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Clock Servo
+//!
+//!   A single PTP exchange gives a noisy offset, a flight delayed in one
+//! direction shifts it by half the delay. The [ClockServo] is a PI loop (like
+//! IEEE 1588 servos) that tracks the offset and the frequency ratio of the
+//! two clocks from successive exchanges:
+//!
+//! o(t) = o + d * (t - t_last), C(t) = H(t) + o(t)
+//!
+//! Each sample's error from the prediction moves the offset by kp * error and
+//! the drift by ki * error / dt. Samples far outside the running error spread
+//! are rejected, so one bad flight doesn't move the estimate.
+//!
+//! Everything is no_std, the firmware can run the same servo on host stamps.
+//!
+//! ```
+//! use rid::ptp::{Micros, servo::ClockServo};
+//!
+//! let mut servo = ClockServo::default();
+//!
+//! // client runs 1s ahead and 100ppm fast
+//! for i in 0..5_000u64 {
+//!
+//!     let host = Micros::new(i * 10_000);
+//!     let offset = 1_000_000 + i as i64;
+//!
+//!     servo.update(host, offset);
+//!
+//! }
+//!
+//! assert!((servo.ratio() - 1.0001).abs() < 1E-6);
+//! assert!((servo.offset(Micros::new(50_000_000)) - 1_005_000).abs() <= 1);
+//! ```
+
+use crate::ptp::{Micros, ptp_offset, round, sqrt};
+
+/// Default proportional gain of a [ClockServo]
+pub const SERVO_DEFAULT_KP: f64 = 0.02;
+/// Default integral gain of a [ClockServo]
+pub const SERVO_DEFAULT_KI: f64 = 0.00005;
+/// Weight of each new error in the running error variance
+pub const SERVO_VARIANCE_WEIGHT: f64 = 1.0 / 16.0;
+/// Consecutive rejected samples before the servo assumes the clock stepped
+pub const SERVO_MAX_OUTLIERS: usize = 8;
+
+/// PI loop that tracks the offset and frequency ratio between two clocks
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ClockServo {
+    /// Proportional gain, fraction of the error applied to the offset
+    pub kp: f64,
+    /// Integral gain, fraction of the error rate applied to the drift
+    pub ki: f64,
+    /// Samples with errors larger than this many standard deviations are rejected
+    pub outlier_sigma: f64,
+    /// Smallest standard deviation used to reject samples (microseconds)
+    pub min_sigma: f64,
+
+    offset: f64,
+    drift: f64,
+    variance: f64,
+    last: Micros,
+
+    samples: usize,
+    rejected: usize,
+    outliers: usize,
+}
+
+impl Default for ClockServo {
+    /// A servo with the default gains, rejects errors over 4 sigma
+    fn default() -> ClockServo {
+
+        ClockServo::new(SERVO_DEFAULT_KP, SERVO_DEFAULT_KI)
+
+    }
+}
+
+impl ClockServo {
+    /// Create a servo with the gains, see [SERVO_DEFAULT_KP] and [SERVO_DEFAULT_KI]
+    pub fn new(kp: f64, ki: f64) -> ClockServo {
+
+        ClockServo {
+            kp,
+            ki,
+            outlier_sigma: 4.0,
+            min_sigma: 20.0,
+
+            offset: 0.0,
+            drift: 0.0,
+            variance: 0.0,
+            last: Micros::ZERO,
+
+            samples: 0,
+            rejected: 0,
+            outliers: 0,
+        }
+
+    }
+
+    /// Forget every sample, the gains are kept
+    pub fn reset(&mut self) {
+
+        *self = ClockServo {
+            outlier_sigma: self.outlier_sigma,
+            min_sigma: self.min_sigma,
+            ..ClockServo::new(self.kp, self.ki)
+        };
+
+    }
+
+    /// Add a PTP exchange, see [ptp_offset()] for the event order.
+    ///
+    /// The sample is taken at the host midpoint of the exchange.
+    /// Returns false if the sample was rejected.
+    pub fn sample(&mut self, host_write: Micros, client_read: Micros, client_write: Micros, host_read: Micros) -> bool {
+
+        let offset = ptp_offset(host_write.micros() as i64,
+            client_read.micros() as i64,
+            client_write.micros() as i64,
+            host_read.micros() as i64,
+        );
+
+        self.update(host_write + ((host_read - host_write) / 2), offset)
+
+    }
+
+    /// Add a measured offset (microseconds) at a host time.
+    ///
+    /// The first sample sets the offset, the rest run the PI loop
+    /// (the drift starts at 0). Returns false if the sample was rejected.
+    pub fn update(&mut self, host: Micros, offset: i64) -> bool {
+
+        let measured = offset as f64;
+        let dt = (host - self.last) as f64;
+
+        match self.samples {
+            0 => {
+
+                self.offset = measured;
+
+            },
+
+            _ => {
+
+                let predicted = self.offset + (self.drift * dt);
+                let error = measured - predicted;
+
+                let sigma = sqrt(self.variance).max(self.min_sigma);
+
+                if self.samples > 2 && error.abs() > self.outlier_sigma * sigma {
+
+                    self.rejected += 1;
+                    self.outliers += 1;
+
+                    // that many in a row is a step, not noise
+                    if self.outliers >= SERVO_MAX_OUTLIERS {
+
+                        self.reset();
+                        return self.update(host, offset);
+
+                    }
+
+                    return false;
+
+                }
+
+                self.outliers = 0;
+
+                self.offset = predicted + (self.kp * error);
+
+                if dt > 0.0 {
+                    self.drift += self.ki * error / dt;
+                }
+
+                self.variance += SERVO_VARIANCE_WEIGHT * ((error * error) - self.variance);
+
+            },
+        }
+
+        self.last = host;
+        self.samples += 1;
+
+        true
+
+    }
+
+    /// The estimated offset (microseconds) at a host time, C(t) = H(t) + o(t)
+    pub fn offset(&self, host: Micros) -> i64 {
+
+        round(self.offset + (self.drift * (host - self.last) as f64))
+
+    }
+
+    /// The estimated frequency ratio, client rate / host rate
+    pub fn ratio(&self) -> f64 {
+
+        1.0 + self.drift
+
+    }
+
+    /// Standard deviation of the sample errors (microseconds),
+    /// how far a single exchange lands from the estimate
+    pub fn uncertainty(&self) -> f64 {
+
+        sqrt(self.variance)
+
+    }
+
+    /// Convert a host time to a client time
+    pub fn to_client(&self, host: Micros) -> Micros {
+
+        host + self.offset(host)
+
+    }
+
+    /// Convert a client time to a host time
+    pub fn to_host(&self, client: Micros) -> Micros {
+
+        // the offset barely moves over one offset worth of time,
+        // evaluate it at the uncorrected time
+        client + -self.offset(client + -self.offset(client))
+
+    }
+
+    /// Number of samples accepted since the last reset
+    pub fn samples(&self) -> usize {
+
+        self.samples
+
+    }
+
+    /// Number of samples rejected since the last reset
+    pub fn rejected(&self) -> usize {
+
+        self.rejected
+
+    }
+}
@@ -0,0 +1,248 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # PTP Statistics
+//!
+//!   Running statistics of a link's exchanges, nothing is stored per sample
+//! so the numbers cover the whole run. [PtpStats] is filled in by the host
+//! layer, a [PtpSnapshot] adds the current fit and sync state. With the
+//! "std" feature both serialize with serde, tools can log or show them.
+//!
+//! ```
+//! use rid::ptp::stats::RunningStats;
+//!
+//! let mut stats = RunningStats::default();
+//!
+//! for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+//!     stats.add(x);
+//! }
+//!
+//! assert!((stats.mean - 5.0).abs() < 1E-12);
+//! assert!((stats.std() - 2.0).abs() < 1E-12);
+//! assert_eq!((stats.min, stats.max), (2.0, 9.0));
+//! ```
+
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+
+use crate::ptp::{sqrt, sync::SyncState};
+
+/// Number of bins in a [Histogram]
+pub const HISTOGRAM_BINS: usize = 32;
+/// Default width of a flight time bin (microseconds)
+pub const FLIGHT_BIN_US: u32 = 50;
+
+/// Mean, variance and range of a stream of values (Welford's method)
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct RunningStats {
+    /// Number of values
+    pub count: usize,
+    /// Mean of the values
+    pub mean: f64,
+    /// Population variance of the values
+    pub variance: f64,
+    /// Smallest value, 0 before the first (check [RunningStats::count])
+    pub min: f64,
+    /// Largest value, 0 before the first (check [RunningStats::count])
+    pub max: f64,
+}
+
+impl Default for RunningStats {
+    /// No values, every field is finite so it serializes to any format
+    fn default() -> RunningStats {
+
+        RunningStats {
+            count: 0,
+            mean: 0.0,
+            variance: 0.0,
+            min: 0.0,
+            max: 0.0,
+        }
+
+    }
+}
+
+impl RunningStats {
+    /// Add a value
+    pub fn add(&mut self, x: f64) {
+
+        self.count += 1;
+
+        let last_mean = self.mean;
+
+        self.mean += (x - last_mean) / self.count as f64;
+        self.variance += (((x - last_mean) * (x - self.mean)) - self.variance) / self.count as f64;
+
+        match self.count == 1 {
+            true => {
+
+                self.min = x;
+                self.max = x;
+
+            },
+            false => {
+
+                self.min = self.min.min(x);
+                self.max = self.max.max(x);
+
+            },
+        }
+
+    }
+
+    /// Standard deviation of the values
+    pub fn std(&self) -> f64 {
+
+        sqrt(self.variance)
+
+    }
+}
+
+/// Counts of values in fixed width bins starting at 0
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct Histogram {
+    /// Width of each bin
+    pub bin_width: u32,
+    /// Bin i counts values in [i * bin_width, (i + 1) * bin_width), negatives land in bin 0
+    pub bins: [usize; HISTOGRAM_BINS],
+    /// Number of values past the last bin
+    pub overflow: usize,
+}
+
+impl Default for Histogram {
+    /// A histogram of flight times, [FLIGHT_BIN_US] wide bins
+    fn default() -> Histogram {
+
+        Histogram::new(FLIGHT_BIN_US)
+
+    }
+}
+
+impl Histogram {
+    /// Create an empty histogram
+    pub fn new(bin_width: u32) -> Histogram {
+
+        Histogram {
+            bin_width: bin_width.max(1),
+            bins: [0; HISTOGRAM_BINS],
+            overflow: 0,
+        }
+
+    }
+
+    /// Count a value
+    pub fn add(&mut self, x: i64) {
+
+        match (x.max(0) / self.bin_width as i64) as usize {
+            bin if bin < HISTOGRAM_BINS => self.bins[bin] += 1,
+            _ => self.overflow += 1,
+        }
+
+    }
+
+    /// Number of values counted
+    pub fn count(&self) -> usize {
+
+        self.bins.iter().sum::<usize>() + self.overflow
+
+    }
+}
+
+/// Statistics of every exchange on a link
+#[derive(Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct PtpStats {
+    /// Raw offset of each exchange (microseconds), see [crate::ptp::ptp_offset()]
+    pub offset: RunningStats,
+    /// Time from the host write to the host read (microseconds)
+    pub flight: RunningStats,
+    /// Flight times binned
+    pub flight_histogram: Histogram,
+    /// Host read minus the conversion of the client write to host time (microseconds)
+    pub host_error: RunningStats,
+    /// Client write minus the conversion of the host read to client time (microseconds)
+    pub client_error: RunningStats,
+    /// Number of reports written
+    pub exchanges: usize,
+    /// Number of replies read
+    pub replies: usize,
+}
+
+impl PtpStats {
+    /// Count a report that got no reply
+    pub fn missed(&mut self) {
+
+        self.exchanges += 1;
+
+    }
+
+    /// Count an exchange, the flight time is None for the first reply
+    /// (there is no host write to measure it from)
+    pub fn exchange(&mut self, offset: i64, flight: Option<i64>, errors: (i64, i64)) {
+
+        self.exchanges += 1;
+        self.replies += 1;
+
+        self.offset.add(offset as f64);
+
+        if let Some(flight) = flight {
+
+            self.flight.add(flight as f64);
+            self.flight_histogram.add(flight);
+
+        }
+
+        self.host_error.add(errors.0 as f64);
+        self.client_error.add(errors.1 as f64);
+
+    }
+}
+
+/// The health of a link's PTP at one time
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct PtpSnapshot {
+    /// Full host time of the snapshot (microseconds)
+    pub host_time: u64,
+    /// Host time since the layer started (microseconds)
+    pub host_elapsed: i64,
+    /// Client time between the first and last reply (microseconds)
+    pub client_elapsed: i64,
+    /// Sync state after the last exchange
+    pub sync: SyncState,
+    /// Fraction of recent exchanges without a reply
+    pub loss: f64,
+    /// m in C(t) = m * H(t) + b
+    pub skew: f64,
+    /// Standard deviation of the skew, None until the fit has enough points to estimate it
+    pub skew_std: Option<f64>,
+    /// Client time (microseconds since the client's first reply) at the host start
+    pub intercept: i64,
+    /// Standard deviation of a conversion now (microseconds), None until the fit has enough points to estimate it
+    pub uncertainty: Option<f64>,
+    /// Points in the fit
+    pub points: usize,
+    /// Frequency ratio tracked by the servo
+    pub servo_ratio: f64,
+    /// Exchanges passed by the delay filter
+    pub accepted: usize,
+    /// Exchanges dropped by the delay filter
+    pub rejected: usize,
+    /// Reports from the client that never arrived
+    pub lost: usize,
+    /// Statistics of every exchange
+    pub stats: PtpStats,
+}
@@ -0,0 +1,213 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Sync State
+//!
+//!   Whether time conversions can be trusted right now. A [SyncMonitor] looks
+//! at the [LinearFit] and at which exchanges got a reply once per cycle:
+//!
+//! - [SyncState::Unsynced]: no exchange passed yet
+//! - [SyncState::Acquiring]: the fit is too short or too uncertain
+//! - [SyncState::Locked]: the fit is within [SyncMonitor::lock_uncertainty]
+//! - [SyncState::Holdover]: a locked link stopped replying, the fit is extrapolated
+//! - [SyncState::Faulted]: too many replies were lost or holdover went on too long
+//!
+//! Locked has some hysteresis, it is kept until the uncertainty doubles.
+//! Faulted clears once replies come back and the loss drops.
+//!
+//! ```
+//! use rid::ptp::{Micros, estimator::LinearFit, sync::{SyncMonitor, SyncState}};
+//!
+//! let mut monitor = SyncMonitor::default();
+//! let fit = LinearFit { offset_std: 2.0, drift_std: 1E-6, points: 32, ..LinearFit::default() };
+//!
+//! assert_eq!(monitor.update(Micros::ZERO, &fit, true), SyncState::Locked);
+//!
+//! for i in 1..=4 {
+//!     monitor.update(Micros::new(i * 1_000), &fit, false);
+//! }
+//!
+//! assert_eq!(monitor.state(), SyncState::Holdover);
+//! ```
+
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+
+use crate::{RID_CYCLE_TIME_US, ptp::{Micros, estimator::LinearFit}};
+
+/// Number of recent exchanges the loss is measured over. At most 64,
+/// [SyncMonitor] keeps one bit per exchange in a u64
+pub const SYNC_LOSS_WINDOW: usize = 64;
+
+const _: () = assert!(SYNC_LOSS_WINDOW > 0 && SYNC_LOSS_WINDOW <= u64::BITS as usize, "SYNC_LOSS_WINDOW must be 1 to 64 exchanges");
+
+/// How far time conversions can be trusted
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum SyncState {
+    /// Nothing is known about the remote clock
+    Unsynced,
+    /// Samples are coming in, the fit is not good enough yet
+    Acquiring,
+    /// Conversions are within [SyncMonitor::lock_uncertainty]
+    Locked,
+    /// The link stopped replying after a lock, the fit is extrapolated
+    Holdover,
+    /// The link is too lossy or holdover lasted too long
+    Faulted,
+}
+
+/// Tracks the [SyncState] of a link
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct SyncMonitor {
+    /// Largest standard deviation of a conversion that locks (microseconds)
+    pub lock_uncertainty: f64,
+    /// Holdover faults once a conversion is this uncertain (microseconds)
+    pub fault_uncertainty: f64,
+    /// Fewest points in the fit that lock
+    pub min_points: usize,
+    /// Missed replies in a row that start a holdover
+    pub holdover_misses: usize,
+    /// Fraction of the last [SYNC_LOSS_WINDOW] exchanges that can be lost before faulting
+    pub max_loss: f64,
+
+    state: SyncState,
+    since: Micros,
+
+    /// bit i is set if the exchange i updates ago got no reply
+    history: u64,
+    misses: usize,
+}
+
+impl Default for SyncMonitor {
+    /// Locks at 50us, faults at a cycle of uncertainty or half the replies lost
+    fn default() -> SyncMonitor {
+
+        SyncMonitor {
+            lock_uncertainty: 50.0,
+            fault_uncertainty: RID_CYCLE_TIME_US,
+            min_points: 16,
+            holdover_misses: 4,
+            max_loss: 0.5,
+
+            state: SyncState::Unsynced,
+            since: Micros::ZERO,
+
+            history: 0,
+            misses: 0,
+        }
+
+    }
+}
+
+impl SyncMonitor {
+    /// Back to [SyncState::Unsynced], the thresholds are kept
+    pub fn reset(&mut self) {
+
+        *self = SyncMonitor {
+            state: SyncState::Unsynced,
+            since: Micros::ZERO,
+
+            history: 0,
+            misses: 0,
+
+            ..*self
+        };
+
+    }
+
+    /// Record one exchange and move to the next state.
+    ///
+    /// Call once per cycle with the host time, the latest fit
+    /// and whether the exchange got a reply.
+    pub fn update(&mut self, now: Micros, fit: &LinearFit, replied: bool) -> SyncState {
+
+        self.history = (self.history << 1) | (!replied as u64);
+
+        self.misses = match replied {
+            true => 0,
+            false => self.misses + 1,
+        };
+
+        let uncertainty = fit.uncertainty(now);
+
+        let next = match self.state {
+            _ if fit.points == 0 => SyncState::Unsynced,
+
+            _ if self.loss() > self.max_loss => SyncState::Faulted,
+
+            SyncState::Locked | SyncState::Holdover if self.misses >= self.holdover_misses => {
+
+                match uncertainty > self.fault_uncertainty {
+                    true => SyncState::Faulted,
+                    false => SyncState::Holdover,
+                }
+
+            },
+
+            SyncState::Faulted if self.misses > 0 => SyncState::Faulted,
+
+            _ if self.misses >= self.holdover_misses => SyncState::Acquiring,
+
+            _ if fit.points >= self.min_points && uncertainty <= self.lock_uncertainty => SyncState::Locked,
+
+            // hysteresis, a lock isn't dropped over a little noise
+            SyncState::Locked | SyncState::Holdover if uncertainty <= 2.0 * self.lock_uncertainty => SyncState::Locked,
+
+            _ => SyncState::Acquiring,
+        };
+
+        if next != self.state {
+
+            self.state = next;
+            self.since = now;
+
+        }
+
+        self.state
+
+    }
+
+    /// The current state
+    pub fn state(&self) -> SyncState {
+
+        self.state
+
+    }
+
+    /// True if conversions are within [SyncMonitor::lock_uncertainty]
+    pub fn is_locked(&self) -> bool {
+
+        self.state == SyncState::Locked
+
+    }
+
+    /// Host time the current state started at
+    pub fn since(&self) -> Micros {
+
+        self.since
+
+    }
+
+    /// Fraction of the last [SYNC_LOSS_WINDOW] exchanges that got no reply,
+    /// exchanges before the first update count as replied
+    pub fn loss(&self) -> f64 {
+
+        let window = u64::MAX >> (u64::BITS as usize - SYNC_LOSS_WINDOW);
+
+        (self.history & window).count_ones() as f64 / SYNC_LOSS_WINDOW as f64
+
+    }
+}
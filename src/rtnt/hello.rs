@@ -0,0 +1,205 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Protocol Handshake
+//!
+//!   Before a host pushes any [crate::rtnt::task_manager::TaskNode]s it sends
+//! a [PacketType::Hello] request. The client answers with its [Capabilities]:
+//! protocol version, packet layout, limits and the [TaskDriver]s it was built with.
+//! The host checks its graph against those and refuses to configure a client
+//! that can't run it.
+//!
+
+use crate::{
+    RIDReport, RID_PACKET_SIZE, RID_PROTOCOL_VERSION,
+    rtnt::{*, packet::RidPacket, task_generator::*, task_manager::TaskNode},
+};
+
+/// Maximum number of driver ids a [Capabilities] can list
+pub const MAX_HELLO_DRIVERS: usize = 16;
+
+/// Header value (index 2) of a hello sent by a host
+pub const HELLO_REQUEST: u8 = 0;
+/// Header value (index 2) of a hello sent by a client
+pub const HELLO_REPLY: u8 = 1;
+
+/// Reasons a host refuses to configure a client
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum ConfigError {
+    /// The client speaks a different protocol version
+    Version(u8),
+    /// The client uses a different packet size or header/data layout
+    Layout,
+    /// The graph has more tasks than the client can hold
+    TooManyTasks(usize),
+    /// The task at this index uses a driver the client doesn't have
+    UnsupportedDriver(usize),
+    /// The task at this index has more inputs than the client allows
+    TooManyInputs(usize),
+    /// The task at this index has more outputs than fit in a packet
+    TooManyOutputs(usize),
+    /// The task at this index has more config chunks than the client can store
+    TooManyChunks(usize),
+}
+
+/// Limits and features of one side of a link.
+///
+/// Shared in the data section of [PacketType::Hello] packets.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Capabilities {
+    /// [RID_PROTOCOL_VERSION] of the sender
+    pub version: u8,
+    /// Number of tasks the sender can hold
+    pub max_tasks: u8,
+    /// Number of inputs each task can have
+    pub max_inputs: u8,
+    /// Number of config chunks each task can use
+    pub max_chunks: u8,
+    /// Size of every [RIDReport]
+    pub packet_size: u16,
+    /// Length of the RTNT header
+    pub header_length: u8,
+    /// Number of data bytes in each packet
+    pub data_bytes: u16,
+    /// Number of valid ids in drivers
+    pub n_drivers: u8,
+    /// Ids of the [TaskDriver]s the sender can generate
+    pub drivers: [u8; MAX_HELLO_DRIVERS],
+}
+
+impl Capabilities {
+    /// The capabilities of this build
+    pub fn local() -> Capabilities {
+
+        let mut drivers = [0u8; MAX_HELLO_DRIVERS];
+
+        for (i, driver) in TASK_DRIVERS.iter().enumerate() {
+            drivers[i] = driver.as_u8();
+        }
+
+        Capabilities {
+            version: RID_PROTOCOL_VERSION,
+            max_tasks: MAX_TASKS as u8,
+            // tasks gather their inputs into MAX_TASK_DATA_FLOATS floats, see RidPacket::decode()
+            max_inputs: MAX_TASK_INPUTS.min(MAX_TASK_DATA_FLOATS) as u8,
+            max_chunks: MAX_TASK_CONFIG_CHUNKS as u8,
+            packet_size: RID_PACKET_SIZE as u16,
+            header_length: RTNT_HDR_LENGTH as u8,
+            data_bytes: MAX_TASK_DATA_BYTES as u16,
+            n_drivers: TASK_DRIVERS.len() as u8,
+            drivers,
+        }
+    }
+
+    /// Copy Self into a [TaskBuffer]
+    pub fn encode(&self) -> TaskBuffer {
+
+        let mut buffer = [0u8; MAX_TASK_DATA_BYTES];
+
+        buffer[0] = self.version;
+        buffer[1] = self.max_tasks;
+        buffer[2] = self.max_inputs;
+        buffer[3] = self.max_chunks;
+        buffer[4..6].copy_from_slice(&self.packet_size.to_be_bytes());
+        buffer[6] = self.header_length;
+        buffer[7..9].copy_from_slice(&self.data_bytes.to_be_bytes());
+        buffer[9] = self.n_drivers;
+        buffer[10..10+MAX_HELLO_DRIVERS].copy_from_slice(&self.drivers);
+
+        buffer
+
+    }
+
+    /// Read Self from the data section of a [PacketType::Hello] packet
+    pub fn decode(buffer: &[u8]) -> Capabilities {
+
+        let mut drivers = [0u8; MAX_HELLO_DRIVERS];
+        drivers.copy_from_slice(&buffer[10..10+MAX_HELLO_DRIVERS]);
+
+        Capabilities {
+            version: buffer[0],
+            max_tasks: buffer[1],
+            max_inputs: buffer[2],
+            max_chunks: buffer[3],
+            packet_size: u16::from_be_bytes([buffer[4], buffer[5]]),
+            header_length: buffer[6],
+            data_bytes: u16::from_be_bytes([buffer[7], buffer[8]]),
+            n_drivers: buffer[9].min(MAX_HELLO_DRIVERS as u8),
+            drivers,
+        }
+    }
+
+    /// Check if the sender can generate a [TaskDriver]
+    pub fn supports(&self, driver: &TaskDriver) -> bool {
+
+        self.drivers[..self.n_drivers as usize].contains(&driver.as_u8())
+
+    }
+
+    /// Check that both sides agree on the version and packet layout.
+    /// Packets can't be parsed by the remote if any of these differ.
+    pub fn check_layout(&self) -> Result<(), ConfigError> {
+
+        let local = Capabilities::local();
+
+        if self.version != local.version {
+            return Err(ConfigError::Version(self.version));
+        }
+
+        match self.packet_size == local.packet_size && self.header_length == local.header_length && self.data_bytes == local.data_bytes {
+            true => Ok(()),
+            false => Err(ConfigError::Layout),
+        }
+    }
+
+    /// Check that the sender can run a [TaskNode] at the given index
+    pub fn check_node(&self, index: usize, node: &TaskNode) -> Result<(), ConfigError> {
+
+        if index >= self.max_tasks as usize {
+            return Err(ConfigError::TooManyTasks(index + 1));
+        }
+
+        if let Some(driver) = &node.driver {
+
+            if !self.supports(driver) {
+                return Err(ConfigError::UnsupportedDriver(index));
+            }
+
+        }
+
+        if node.n_inputs > self.max_inputs {
+            return Err(ConfigError::TooManyInputs(index));
+        }
+
+        if node.n_outputs as usize > self.data_bytes as usize / 4 {
+            return Err(ConfigError::TooManyOutputs(index));
+        }
+
+        match node.config_cache.chunks() > self.max_chunks as usize {
+            true => Err(ConfigError::TooManyChunks(index)),
+            false => Ok(()),
+        }
+    }
+
+    /// Build a sealed [PacketType::Hello] packet carrying Self.
+    ///
+    /// `kind` is [HELLO_REQUEST] on hosts and [HELLO_REPLY] on clients.
+    pub fn hello(&self, kind: u8) -> RIDReport {
+
+        RidPacket::Hello { kind, capabilities: *self }.encode()
+
+    }
+}
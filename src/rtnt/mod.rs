@@ -13,15 +13,30 @@
 //!
 //!
 //! # Real Time Numerical Tasks
+//!
+//!   Each report has an 8 byte RTNT header, then the task data, then
+//! the checksum and sequence number ([crate::link]) and the PTP stamps.
+//! A task's outputs and every config chunk have to fit in the data,
+//! [MAX_TASK_DATA_BYTES] is [crate::RID_PACKET_SIZE] - 28:
+//!
+//! | report size | data bytes | task outputs |
+//! |-------------|------------|--------------|
+//! | 64          | 36         | 9            |
+//! | 512         | 484        | 121          |
+//! | 1024        | 996        | 249          |
+//!
+//!   Before protocol version 3 ([crate::RID_PROTOCOL_VERSION]) a 64 byte
+//! report carried 40 data bytes (10 outputs), nodes with 10 outputs have
+//! to be split. The handshake refuses them ([hello::ConfigError::TooManyOutputs]).
+
+use crate::{RIDReport, RID_CRC_INDEX};
 
-use crate::{RIDReport, RID_PACKET_SIZE, RID_TASK_INDEX, RID_MODE_INDEX, RID_PTP_RESERVED_BYTES};
+use packet::RidPacket;
 
 /// Maximum number of tasks user can spawn
 pub const MAX_TASKS: usize = 32;
 /// Maximum number of bytes a task can use in its name
 pub const MAX_TASK_INPUTS: usize = 16;
-/// Maximum number of configuration chunks each task can use
-pub const MAX_TASK_CONFIG_CHUNKS: usize = 32;
 
 /// Cnostant
 /// RTNT Header index
@@ -30,10 +45,23 @@ pub const RTNT_HDR_INDEX: usize = 0;
 pub const RTNT_HDR_LENGTH: usize = 8;
 /// RTNT Data start
 pub const RTNT_DATA_INDEX: usize = RTNT_HDR_LENGTH + RTNT_HDR_INDEX;
-/// Maximum number of bytes in a Tasks output buffer and configuration chunk
-pub const MAX_TASK_DATA_BYTES: usize = RID_PACKET_SIZE - RTNT_DATA_INDEX - RID_PTP_RESERVED_BYTES;
+/// Maximum number of bytes in a Tasks output buffer and configuration chunk,
+/// what is left of a report after the header, checksum, sequence number and PTP stamps
+pub const MAX_TASK_DATA_BYTES: usize = RID_CRC_INDEX - RTNT_DATA_INDEX;
 /// Maximum nuber of floats in a tasks output (use f32, not worried about precision rn)
 pub const MAX_TASK_DATA_FLOATS: usize = MAX_TASK_DATA_BYTES / 4;
+/// Maximum number of configuration bytes each task can use (32 chunks of a 64 byte report)
+pub const MAX_TASK_CONFIG_BYTES: usize = 1152;
+/// Maximum number of configuration chunks each task can use, bigger 
+/// packets need fewer chunks to share the same configuration
+pub const MAX_TASK_CONFIG_CHUNKS: usize = MAX_TASK_CONFIG_BYTES.div_ceil(MAX_TASK_DATA_BYTES);
+
+// A status packet lists every chunk in its data
+const _: () = assert!(MAX_TASK_CONFIG_CHUNKS <= MAX_TASK_DATA_BYTES);
+// The module docs list the data bytes of each report size
+const _: () = assert!(MAX_TASK_DATA_BYTES == crate::RID_PACKET_SIZE - 28);
+/// Task id of packets for the link instead of a task (hello, PTP probes)
+pub const RTNT_LINK_ID: u8 = 255;
 
 /// alias for readability
 pub type TaskHeader = [u8; RTNT_HDR_LENGTH];
@@ -69,7 +97,7 @@ pub trait RTNTask {
 
 /// Specifies the state a Task is in
 /// and the action required by the [crate::rtnt::task_manager::TaskManager]
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub enum TaskStatus {
     /// The task is panicing due to a runtime/configuration error
@@ -130,6 +158,10 @@ pub enum PacketType {
     Data,
     /// kill
     Kill,
+    /// protocol handshake, see [crate::rtnt::hello]
+    Hello,
+    /// time triggered command, see [crate::rtnt::trigger]
+    Trigger,
 }
 
 impl PacketType {
@@ -140,6 +172,8 @@ impl PacketType {
             1 => PacketType::Chunk,
             2 => PacketType::Status,           
             3 => PacketType::Kill,           
+            4 => PacketType::Hello,
+            5 => PacketType::Trigger,
             _ => PacketType::Data,
         }
     }
@@ -152,6 +186,8 @@ impl PacketType {
             PacketType::Chunk => 1,
             PacketType::Status => 2,           
             PacketType::Kill => 3,           
+            PacketType::Hello => 4,
+            PacketType::Trigger => 5,
         }
     }
 }
@@ -304,90 +340,81 @@ impl TaskConfig {
     /// If the id changes it indicates a new configuration
     /// is available. When a new configuration becomes available
     /// the buffer will reset, then consume the chunk.
-    pub fn collect_chunk(&mut self, header: &[u8], buffer: &[u8]) {
-
-        let id = header[2];
-        let chunk_num = header[3] as usize;
+    pub fn collect_chunk(&mut self, id: u8, chunk_num: usize, total_chunks: usize, buffer: &TaskBuffer) {
 
-        self.total_chunks = header[4] as usize;
+        self.total_chunks = total_chunks;
 
         match self.id == id {
             true => {
 
-                self.new_chunk(chunk_num, &buffer[..MAX_TASK_DATA_BYTES]);
+                self.new_chunk(chunk_num, buffer);
 
             },
             _ => {
 
                 self.id = id;
                 self.reset_chunks();
-                self.new_chunk(chunk_num, &buffer[..MAX_TASK_DATA_BYTES]);
+                self.new_chunk(chunk_num, buffer);
 
             },
         }
     }
 
-    /// Copy the chunk status from the buffer.
+    /// Copy the chunk status from a [RidPacket::Status].
     ///
     /// This will only happen on hosts trying to
     /// configure a set of tasks.
-    pub fn collect_status(&mut self, buffer: &[u8]) -> usize {
+    pub fn collect_status(&mut self, missing: &[bool; MAX_TASK_CONFIG_CHUNKS]) -> usize {
 
-        for i in 0..self.total_chunks {
-            self.missing_chunks[i] = buffer[i] != 0;
-        }
+        self.missing_chunks[..self.total_chunks].copy_from_slice(&missing[..self.total_chunks]);
 
         self.missing_chunks()
 
     }
 
-    /// Copy the configuration data to a buffer
+    /// Build a [RidPacket::Chunk] with the first missing chunk
     ///
     /// Only happens on the host.
     ///
     /// This assumes the missing chunks feild is
     /// syncronized with the client. That happens
     /// when the client sends a packet with the status.
-    pub fn emit_chunk(&self) -> (TaskHeader, TaskBuffer) {
+    pub fn emit_chunk(&self, task: usize) -> RidPacket {
 
-        let mut header = [0u8; RTNT_HDR_LENGTH];
         let mut buffer = [0u8; MAX_TASK_DATA_BYTES];
 
         let chunk_num = match self.first_missing() {
             Some(chunk_num) => {
                 buffer[..MAX_TASK_DATA_BYTES].copy_from_slice(&self.buffer[chunk_num]);
 
-                chunk_num as u8
+                chunk_num
             }
             _ => 0,
         };
 
-        header[2] = self.id;
-        header[3] = chunk_num;
-        header[4] = self.total_chunks as u8;
-
-        (
-            header,
-            buffer
-        )
+        RidPacket::Chunk {
+            task,
+            id: self.id,
+            chunk: chunk_num,
+            total: self.total_chunks,
+            data: buffer,
+        }
     }
 
-    /// Copy the status data to a buffer
+    /// Build a [RidPacket::Status] with the missing chunks
     ///
     /// Only happens on the client.
     ///
     /// This synchronizes the missing data with the host. 
     /// This should only happen when the client is configuring
     /// the task and the state is [TaskStatus::Configuration]
-    pub fn emit_status(&self) -> TaskBuffer {
+    pub fn emit_status(&self, task: usize) -> RidPacket {
 
-        let mut buffer = [0u8; MAX_TASK_DATA_BYTES];
+        let mut missing = [false; MAX_TASK_CONFIG_CHUNKS];
 
-        for i in 0..self.total_chunks {
-            buffer[i] = self.missing_chunks[i] as u8;
-        }
+        missing[..self.total_chunks].copy_from_slice(&self.missing_chunks[..self.total_chunks]);
 
-        buffer
+        RidPacket::Status { task, missing }
 
     }
 
@@ -409,10 +436,8 @@ impl TaskConfig {
 pub struct TaskDataCache {
     /// current task to publish
     context: usize,
-    /// Empty buffer for empty inputs
-    empty: TaskBuffer,
-    /// buffer of task data
-    buffer: [RIDReport; MAX_TASKS],
+    /// unpublished packet of each task
+    buffer: [Option<RidPacket>; MAX_TASKS],
 }
 
 impl TaskDataCache {
@@ -421,133 +446,56 @@ impl TaskDataCache {
     pub fn default() -> TaskDataCache {
         TaskDataCache {
             context: 0,
-            empty: [0u8; MAX_TASK_DATA_BYTES],
-            buffer: [[0u8; RID_PACKET_SIZE]; MAX_TASKS],
+            buffer: [None; MAX_TASKS],
         }
     }
 
-    /// Use the context to find the first task with unpublished output (buffer[RID_TASK_INDEX] > 0).
-    /// Copy the output to a new buffer and clear the mode index.
+    /// Use the context to find the first task with an unpublished packet.
+    /// Takes the packet and encodes it to a sealed report.
     pub fn publish(&mut self, context_wrap: usize) -> Option<RIDReport> {
-        let mut ctr = 0;
-        let mut publish_buffer = [0u8; RID_PACKET_SIZE];
 
-        while ctr < context_wrap {
-        
-            if let Some(publish_buffer) = match self.buffer[self.context][RID_TASK_INDEX] > 0 {
-        
-                true => {
+        for _ in 0..context_wrap {
 
-                    publish_buffer.copy_from_slice(&self.buffer[self.context][..RID_PACKET_SIZE]);
-                    Some(publish_buffer)
-                
-                },
-        
-                false => {
+            let packet = self.buffer[self.context].take();
+            self.context = (self.context + 1) % context_wrap;
 
-                    ctr += 1;
-                    None
-                
-                }
-        
-            } {
+            if let Some(packet) = packet {
 
-                self.buffer[self.context][RID_TASK_INDEX] = 0; // No data available
-                self.context = (self.context + 1) % context_wrap;
+                return Some(packet.encode());
 
-                return Some(publish_buffer);
-            
             }
-            
-            self.context = (self.context + 1) % context_wrap;
-        
+
         }
 
         None
     }
 
-    /// If the buffer has an unpublished [PacketType::Status]
+    /// If the buffer has an unpublished [RidPacket::Status]
     /// Used to prevent the client from overwriting status packets with
     /// data packets (Don't stream data without telling the host the task is ready).
     pub fn status_waiting(&self, index: usize) -> bool {
 
-        self.buffer[index][RID_TASK_INDEX] != 0 && self.buffer[index][RID_MODE_INDEX] == PacketType::Status.as_u8()
+        matches!(self.buffer[index], Some(RidPacket::Status { .. }))
 
     }
 
-    /// Mutable reference to a tasks buffer
-    pub fn task_buffer(&mut self, index: usize) -> &[u8] {
-        
-        if index >= MAX_TASKS { panic!("Invalid index to TaskDataCache {index}"); }
-        
-        &self.buffer[index]
-    
-    }
-
-    /// Publish a new status and TaskBuffer to a tasks cache. Will
-    /// always overwrite unpublished packets. If that is not desired
+    /// Queue a packet in a tasks cache. Will always overwrite
+    /// unpublished packets. If that is not desired
     /// The buffer should be checked before hand.
-    pub fn new_output(&mut self, index: usize, status: PacketType, buffer: TaskBuffer) {
-        
-        if index >= MAX_TASKS { panic!("Invalid index to TaskDataCache {index}"); }
-
-
-        self.buffer[index][RID_TASK_INDEX] = index as u8 + 1;
-        self.buffer[index][RID_MODE_INDEX] = status.as_u8();
-
-        self.buffer[index][RTNT_DATA_INDEX..RTNT_DATA_INDEX+MAX_TASK_DATA_BYTES].copy_from_slice(&buffer[..MAX_TASK_DATA_BYTES]);
-
-    }
-
-    /// Write a new mode and TaskBuffer to a task's cache. This packet can always be overwritten and
-    /// will not stream to remote instances. 
-    pub fn new_nonstreaming_output(&mut self, index: usize, mode: PacketType, buffer: TaskBuffer) {
+    pub fn queue(&mut self, index: usize, packet: RidPacket) {
         
         if index >= MAX_TASKS { panic!("Invalid index to TaskDataCache {index}"); }
 
-        self.buffer[index][RID_TASK_INDEX] = 0;
-        self.buffer[index][RID_MODE_INDEX] = mode.as_u8();
-        
-        self.buffer[index][RTNT_DATA_INDEX..RTNT_DATA_INDEX+MAX_TASK_DATA_BYTES].copy_from_slice(&buffer[..MAX_TASK_DATA_BYTES]);
-    
-    }
+        self.buffer[index] = Some(packet);
 
-    /// Publish a new [PacketType], [TaskHeader] and [TaskBuffer].
-    /// This function will overwrite any existing data.
-    pub fn new_output_with_header(&mut self, index: usize, mode: PacketType, mut header: TaskHeader, buffer: TaskBuffer) {
-        
-        if index >= MAX_TASKS { panic!("Invalid index to TaskDataCache {index}"); }
-
-        header[RID_TASK_INDEX] = index as u8 + 1;
-        header[RID_MODE_INDEX] = mode.as_u8();
-        
-        self.buffer[index][RTNT_HDR_INDEX..RTNT_DATA_INDEX].copy_from_slice(&header);
-        self.buffer[index][RTNT_DATA_INDEX..RTNT_DATA_INDEX+MAX_TASK_DATA_BYTES].copy_from_slice(&buffer[..MAX_TASK_DATA_BYTES]);
-    
-    }
-
-    /// Get a reference to all the requested task buffers.
-    /// Not a mutable reference and does not make any garuantees
-    /// about the data behind the reference.
-    pub fn task_input_buffer(&self, inputs: &[u8; MAX_TASK_INPUTS]) -> [&[u8]; MAX_TASK_INPUTS] {
-        
-        core::array::from_fn(|i| {
-
-            match inputs[i as usize] < MAX_TASKS as u8 { 
-            
-                true => &self.buffer[inputs[i as usize] as usize][RTNT_DATA_INDEX..RTNT_DATA_INDEX+MAX_TASK_DATA_BYTES],
-            
-                false => &self.empty,
-            
-            }
-            
-        })
-    
     }
 }
 
+pub mod hello;
+pub mod packet;
 pub mod task_generator;
 pub mod task_manager;
+pub mod trigger;
 
 pub mod switch;
 pub mod constant;
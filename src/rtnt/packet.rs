@@ -0,0 +1,396 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Packet Codec
+//!
+//!   Every [RIDReport] the task managers share is built and parsed here.
+//! Nothing else should index into a report's task, mode, header or data bytes.
+//!
+//! Layout: `[task id][mode][header 2..8][data][crc][sequence][ptp]`
+//!
+//! Task ids start at 1, 0 is an idle report and [RTNT_LINK_ID] is for
+//! packets about the link (hello, triggers, PTP probes). The sequence number and
+//! PTP stamps are written at send time by [crate::link] and [crate::ptp::TimeStamp].
+//!
+//! ```
+//! use rid::rtnt::{MAX_TASK_DATA_FLOATS, packet::RidPacket};
+//!
+//! let packet = RidPacket::Data { task: 2, data: [1.0; MAX_TASK_DATA_FLOATS] };
+//! let report = packet.encode();
+//!
+//! assert!(RidPacket::decode(&report) == Ok(packet));
+//! ```
+
+use crate::{
+    RIDReport, RID_PACKET_SIZE, RID_TASK_INDEX, RID_MODE_INDEX,
+    link,
+    ptp::{Micros, USEC_PER_HOUR},
+    rtnt::{*, hello::Capabilities, task_generator::TaskDriver, trigger::{Trigger, TriggerAction}},
+};
+
+/// Reasons a [RIDReport] can't be decoded
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum DecodeError {
+    /// The checksum doesn't match the packet
+    Checksum,
+    /// No task or link packet has this id
+    InvalidTask(u8),
+    /// No [PacketType] has this value
+    InvalidMode(u8),
+    /// A header value is out of range (inputs, chunks, hello or trigger kind, action)
+    /// or an input points past the tasks or their outputs
+    InvalidHeader,
+}
+
+/// A decoded [RIDReport]
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum RidPacket {
+    /// Nothing to share
+    Idle,
+    /// Only carries PTP stamps
+    Ptp,
+    /// Header data for a [crate::rtnt::task_manager::TaskNode]
+    Init {
+        /// index of the task (starts at 0)
+        task: usize,
+        /// stream the task's output
+        stream: u8,
+        /// rate the task executes at
+        rate: u16,
+        /// driver that generates the task
+        driver: TaskDriver,
+        /// number of inputs
+        n_inputs: u8,
+        /// number of outputs
+        n_outputs: u8,
+        /// ids and indices of each input
+        inputs: InputIDBuffer,
+    },
+    /// A chunk of configuration data
+    Chunk {
+        /// index of the task (starts at 0)
+        task: usize,
+        /// id of the configuration the chunk belongs to
+        id: u8,
+        /// number of this chunk
+        chunk: usize,
+        /// number of chunks in the configuration
+        total: usize,
+        /// the chunk
+        data: TaskBuffer,
+    },
+    /// Missing chunks of a task's configuration
+    Status {
+        /// index of the task (starts at 0)
+        task: usize,
+        /// non zero for each missing chunk
+        missing: [bool; MAX_TASK_CONFIG_CHUNKS],
+    },
+    /// Output of a streamed task
+    Data {
+        /// index of the task (starts at 0)
+        task: usize,
+        /// the output
+        data: TaskData,
+    },
+    /// Kill every task
+    Kill,
+    /// Protocol handshake, see [crate::rtnt::hello]
+    Hello {
+        /// [crate::rtnt::hello::HELLO_REQUEST] or [crate::rtnt::hello::HELLO_REPLY]
+        kind: u8,
+        /// the sender's capabilities
+        capabilities: Capabilities,
+    },
+    /// Time triggered command, see [crate::rtnt::trigger]
+    Trigger {
+        /// [crate::rtnt::trigger::TRIGGER_REQUEST] or [crate::rtnt::trigger::TRIGGER_ACK]
+        kind: u8,
+        /// the command
+        trigger: Trigger,
+    },
+}
+
+impl RidPacket {
+
+    /// Write Self to a new [RIDReport].
+    ///
+    /// Everything except [RidPacket::Idle] and [RidPacket::Ptp]
+    /// is sealed with a checksum.
+    pub fn encode(&self) -> RIDReport {
+
+        let mut report = [0u8; RID_PACKET_SIZE];
+
+        let header = RTNT_HDR_INDEX;
+        let data = RTNT_DATA_INDEX;
+
+        match self {
+            RidPacket::Idle => return report,
+
+            RidPacket::Ptp => {
+
+                report[RID_TASK_INDEX] = RTNT_LINK_ID;
+                report[RID_MODE_INDEX] = PacketType::Data.as_u8();
+                return report;
+
+            },
+
+            RidPacket::Init { task, stream, rate, driver, n_inputs, n_outputs, inputs } => {
+
+                report[RID_TASK_INDEX] = *task as u8 + 1;
+                report[RID_MODE_INDEX] = PacketType::Init.as_u8();
+                report[header + 2] = *stream;
+                report[header + 3..header + 5].copy_from_slice(&rate.to_be_bytes());
+                report[header + 5] = driver.as_u8();
+                report[header + 6] = *n_inputs;
+                report[header + 7] = *n_outputs;
+
+                for i in 0..(*n_inputs as usize).min(MAX_TASK_INPUTS) {
+                    report[data + (2*i)] = inputs[i][0];
+                    report[data + (2*i) + 1] = inputs[i][1];
+                }
+
+            },
+
+            RidPacket::Chunk { task, id, chunk, total, data: chunk_data } => {
+
+                report[RID_TASK_INDEX] = *task as u8 + 1;
+                report[RID_MODE_INDEX] = PacketType::Chunk.as_u8();
+                report[header + 2] = *id;
+                report[header + 3] = *chunk as u8;
+                report[header + 4] = *total as u8;
+                report[data..data + MAX_TASK_DATA_BYTES].copy_from_slice(chunk_data);
+
+            },
+
+            RidPacket::Status { task, missing } => {
+
+                report[RID_TASK_INDEX] = *task as u8 + 1;
+                report[RID_MODE_INDEX] = PacketType::Status.as_u8();
+
+                for i in 0..MAX_TASK_CONFIG_CHUNKS.min(MAX_TASK_DATA_BYTES) {
+                    report[data + i] = missing[i] as u8;
+                }
+
+            },
+
+            RidPacket::Data { task, data: values } => {
+
+                report[RID_TASK_INDEX] = *task as u8 + 1;
+                report[RID_MODE_INDEX] = PacketType::Data.as_u8();
+
+                for i in 0..MAX_TASK_DATA_FLOATS {
+                    report[data + (4*i)..data + (4*i) + 4].copy_from_slice(&values[i].to_be_bytes());
+                }
+
+            },
+
+            RidPacket::Kill => {
+
+                report[RID_TASK_INDEX] = 1;
+                report[RID_MODE_INDEX] = PacketType::Kill.as_u8();
+
+            },
+
+            RidPacket::Hello { kind, capabilities } => {
+
+                report[RID_TASK_INDEX] = RTNT_LINK_ID;
+                report[RID_MODE_INDEX] = PacketType::Hello.as_u8();
+                report[header + 2] = *kind;
+                report[data..data + MAX_TASK_DATA_BYTES].copy_from_slice(&capabilities.encode());
+
+            },
+
+            RidPacket::Trigger { kind, trigger } => {
+
+                report[RID_TASK_INDEX] = RTNT_LINK_ID;
+                report[RID_MODE_INDEX] = PacketType::Trigger.as_u8();
+                report[header + 2] = *kind;
+                report[header + 3] = trigger.id;
+                report[header + 4] = trigger.action.as_u8();
+                report[data..data + 4].copy_from_slice(&trigger.at.wrapped().to_be_bytes());
+                report[data + 4..data + 8].copy_from_slice(&trigger.tasks.to_be_bytes());
+
+            },
+        }
+
+        link::seal(&mut report);
+
+        report
+
+    }
+
+    /// Read a [RIDReport].
+    ///
+    /// Idle and PTP packets are not checked, all others
+    /// must pass [link::verify()].
+    pub fn decode(report: &RIDReport) -> Result<RidPacket, DecodeError> {
+
+        let task_id = report[RID_TASK_INDEX];
+        let mode_id = report[RID_MODE_INDEX];
+
+        // PacketType::new() maps unknown values to Data,
+        // only accept the value it would encode to.
+        let mode = PacketType::new(mode_id);
+
+        if task_id == 0 {
+            return Ok(RidPacket::Idle);
+        }
+
+        if task_id == RTNT_LINK_ID && mode != PacketType::Hello && mode != PacketType::Trigger {
+            return Ok(RidPacket::Ptp);
+        }
+
+        if task_id as usize > MAX_TASKS && task_id != RTNT_LINK_ID {
+            return Err(DecodeError::InvalidTask(task_id));
+        }
+
+        if !link::verify(report) {
+            return Err(DecodeError::Checksum);
+        }
+
+        if mode.as_u8() != mode_id {
+            return Err(DecodeError::InvalidMode(mode_id));
+        }
+
+        let task = task_id as usize - 1;
+        let header = &report[RTNT_HDR_INDEX..RTNT_DATA_INDEX];
+        let data = &report[RTNT_DATA_INDEX..RTNT_DATA_INDEX + MAX_TASK_DATA_BYTES];
+
+        match mode {
+            PacketType::Init => {
+
+                let n_inputs = header[6];
+
+                // a task runs on at most MAX_TASK_DATA_FLOATS inputs
+                if n_inputs as usize > MAX_TASK_INPUTS.min(MAX_TASK_DATA_FLOATS) {
+                    return Err(DecodeError::InvalidHeader);
+                }
+
+                let mut inputs = [[0u8; 2]; MAX_TASK_INPUTS];
+
+                for i in 0..n_inputs as usize {
+
+                    // <task index, output index>, the client reads nodes[task].data[output]
+                    if data[2*i] as usize >= MAX_TASKS || data[(2*i) + 1] as usize >= MAX_TASK_DATA_FLOATS {
+                        return Err(DecodeError::InvalidHeader);
+                    }
+
+                    inputs[i] = [data[2*i], data[(2*i) + 1]];
+
+                }
+
+                Ok(RidPacket::Init {
+                    task,
+                    stream: header[2],
+                    rate: u16::from_be_bytes([header[3], header[4]]),
+                    driver: TaskDriver::new(header[5]),
+                    n_inputs,
+                    n_outputs: header[7],
+                    inputs,
+                })
+
+            },
+
+            PacketType::Chunk => {
+
+                let chunk = header[3] as usize;
+                let total = header[4] as usize;
+
+                if chunk >= MAX_TASK_CONFIG_CHUNKS || total > MAX_TASK_CONFIG_CHUNKS {
+                    return Err(DecodeError::InvalidHeader);
+                }
+
+                let mut chunk_data = [0u8; MAX_TASK_DATA_BYTES];
+                chunk_data.copy_from_slice(data);
+
+                Ok(RidPacket::Chunk { task, id: header[2], chunk, total, data: chunk_data })
+
+            },
+
+            PacketType::Status => {
+
+                let mut missing = [false; MAX_TASK_CONFIG_CHUNKS];
+
+                for i in 0..MAX_TASK_CONFIG_CHUNKS.min(MAX_TASK_DATA_BYTES) {
+                    missing[i] = data[i] != 0;
+                }
+
+                Ok(RidPacket::Status { task, missing })
+
+            },
+
+            PacketType::Data => {
+
+                let mut values = [0.0f32; MAX_TASK_DATA_FLOATS];
+
+                for i in 0..MAX_TASK_DATA_FLOATS {
+                    values[i] = f32::from_be_bytes([data[4*i], data[(4*i) + 1], data[(4*i) + 2], data[(4*i) + 3]]);
+                }
+
+                Ok(RidPacket::Data { task, data: values })
+
+            },
+
+            PacketType::Kill => Ok(RidPacket::Kill),
+
+            PacketType::Hello => {
+
+                if task_id != RTNT_LINK_ID || header[2] > 1 {
+                    return Err(DecodeError::InvalidHeader);
+                }
+
+                Ok(RidPacket::Hello { kind: header[2], capabilities: Capabilities::decode(data) })
+
+            },
+
+            PacketType::Trigger => {
+
+                let action = match TriggerAction::new(header[4]) {
+                    Some(action) if task_id == RTNT_LINK_ID && header[2] <= 1 => action,
+                    _ => return Err(DecodeError::InvalidHeader),
+                };
+
+                // microseconds into the hour, see Trigger::due_in()
+                let at = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+
+                let trigger = Trigger {
+                    id: header[3],
+                    at: Micros::new(at as u64 % USEC_PER_HOUR),
+                    action,
+                    tasks: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+                };
+
+                Ok(RidPacket::Trigger { kind: header[2], trigger })
+
+            },
+        }
+
+    }
+
+    /// The index of the task this packet is for
+    pub fn task(&self) -> Option<usize> {
+
+        match self {
+            RidPacket::Init { task, .. } |
+            RidPacket::Chunk { task, .. } |
+            RidPacket::Status { task, .. } |
+            RidPacket::Data { task, .. } => Some(*task),
+            _ => None,
+        }
+
+    }
+}
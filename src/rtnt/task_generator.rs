@@ -29,7 +29,7 @@ use crate::{
 
 /// Variants that specify which
 /// [TaskExecutable] to initialize.
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub enum TaskDriver {
 	/// A simple switch with two inputs and one output (TaskBuffer)
@@ -38,6 +38,9 @@ pub enum TaskDriver {
 	Constant,
 }
 
+/// Every [TaskDriver] this build can generate
+pub const TASK_DRIVERS: [TaskDriver; 2] = [TaskDriver::Switch, TaskDriver::Constant];
+
 impl TaskDriver {
 	/// Create Self from a u8
 	pub fn new(id: u8) -> TaskDriver {
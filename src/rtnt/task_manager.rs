@@ -19,9 +19,10 @@
 //!
 
 use crate::{
-    RID_TASK_INDEX, RID_MODE_INDEX,
     RIDReport, 
-    rtnt::{*, task_generator::*},
+    link,
+    ptp::Micros,
+    rtnt::{*, task_generator::*, hello::*, packet::*, trigger::*},
 };
 
 /// Node containing an executable, stream, rate, inputs and status
@@ -42,6 +43,8 @@ pub struct TaskNode {
 
     /// Status of the task, enables and disables running the task
     pub status: TaskStatus,
+    /// An active task does not run while held, see [TriggerAction::Stop]
+    pub held: bool,
     /// Buffer containing configuration data for the task
     pub config_cache: TaskConfig,
 
@@ -66,6 +69,7 @@ impl TaskNode {
             data: [0.0f32; MAX_TASK_DATA_FLOATS],
 
             status: TaskStatus::Standby,
+            held: false,
             config_cache: TaskConfig::default(),
 
             driver: None,
@@ -92,6 +96,7 @@ impl TaskNode {
             data: [0.0f32; MAX_TASK_DATA_FLOATS],
 
             status: TaskStatus::Standby,
+            held: false,
             config_cache: cache,
 
             driver: Some(driver),
@@ -130,7 +135,7 @@ impl TaskNode {
     
     }
 
-    /// Copy the [TaskNode] header data from a buffer to Self
+    /// Copy the [TaskNode] header data from a [RidPacket::Init] to Self
     /// 
     /// If the driver in the header changes the [TaskNode]
     /// will start configuring for that driver immediately. 
@@ -138,21 +143,13 @@ impl TaskNode {
     /// should recieve init packets.
     ///
     /// Also sets the [TaskExecutable] to the new driver (if it is a new driver).
-    pub fn init(&mut self, header: &[u8], data: &[u8]) {
-        
-        let driver = TaskDriver::new(header[5]);
-
-        self.stream = header[2];
-        self.rate = u16::from_be_bytes([header[3], header[4]]);
-        self.n_inputs = header[6];
-        self.n_outputs = header[7];
-
-        for i in 0..self.n_inputs as usize {
+    pub fn init(&mut self, stream: u8, rate: u16, driver: TaskDriver, n_inputs: u8, n_outputs: u8, inputs: &InputIDBuffer) {
 
-            self.inputs[i][0] = data[2*i];
-            self.inputs[i][1] = data[(2*i)+1];
-        
-        }
+        self.stream = stream;
+        self.rate = rate;
+        self.n_inputs = n_inputs;
+        self.n_outputs = n_outputs;
+        self.inputs = *inputs;
 
         self.config_cache.init();
 
@@ -189,37 +186,33 @@ impl TaskNode {
         self.status = TaskStatus::Configuration;
     }
 
-    /// Used to share the current header of a task.
-    /// This is only useful on a host sending an init packet.
-    pub fn header(&self) -> (TaskHeader, TaskBuffer) {
-        
-        let mut header = [0u8; RTNT_HDR_LENGTH];
-        let mut data = [0u8; MAX_TASK_DATA_BYTES];
-
-        header[2] = self.stream;
-        header[3..5].copy_from_slice(&self.rate.to_be_bytes());
-        header[5] = match &self.driver { Some(driver) => driver.as_u8(), None => 0, };
-        header[6] = self.n_inputs;
-        header[7] = self.n_outputs;
-
-        for i in 0..self.n_inputs as usize {
-            data[2*i] = self.inputs[i][0];
-            data[(2*i)+1] = self.inputs[i][1];
-        }
-        // data[..MAX_TASK_INPUTS].copy_from_slice(&self.inputs);
+    /// Build a [RidPacket::Init] with the current header of a task.
+    /// This is only useful on a host sending an init packet,
+    /// a node without a driver has nothing to share.
+    pub fn init_packet(&self, task: usize) -> Option<RidPacket> {
 
-        (header, data)
+        let driver = self.driver?;
+
+        Some(RidPacket::Init {
+            task,
+            stream: self.stream,
+            rate: self.rate,
+            driver,
+            n_inputs: self.n_inputs,
+            n_outputs: self.n_outputs,
+            inputs: self.inputs,
+        })
 
     }
 
-    /// Collect a packet containing config data.
+    /// Collect a chunk of config data.
     /// The data is only consumed if the [TaskNode] is
     /// already in the [TaskStatus::Configuration] state.
-    pub fn collect_chunk(&mut self, header: &[u8], data: &[u8]) {
+    pub fn collect_chunk(&mut self, id: u8, chunk: usize, total: usize, data: &TaskBuffer) {
         match self.status {
             TaskStatus::Configuration => {
 
-                self.config_cache.collect_chunk(header, data);
+                self.config_cache.collect_chunk(id, chunk, total, data);
 
             }
 
@@ -235,6 +228,7 @@ impl TaskNode {
         self.driver = None;
         self.config_cache.clear_chunks();
         self.status = TaskStatus::Standby;
+        self.held = false;
 
     }
 
@@ -284,15 +278,69 @@ impl TaskNode {
 
 }
 
+/// The nodes of a [TaskManager]. Hosts keep them on the heap, with big
+/// reports ([crate::RID_PACKET_SIZE]) they don't fit on a thread stack.
+#[cfg(feature = "std")]
+pub type TaskNodes = Box<[TaskNode; MAX_TASKS]>;
+/// The nodes of a [TaskManager], clients keep them in place
+#[cfg(not(feature = "std"))]
+pub type TaskNodes = [TaskNode; MAX_TASKS];
+
+/// The output cache of a [TaskManager], on the heap with the [TaskNodes]
+#[cfg(feature = "std")]
+pub type TaskCache = Box<TaskDataCache>;
+/// The output cache of a [TaskManager]
+#[cfg(not(feature = "std"))]
+pub type TaskCache = TaskDataCache;
+
+#[cfg(feature = "std")]
+fn task_nodes() -> (TaskNodes, TaskCache) {
+
+    // collected straight into the heap, the array is never built on the stack
+    let nodes: Box<[TaskNode]> = (0..MAX_TASKS).map(|_| TaskNode::empty()).collect();
+
+    (nodes.try_into().expect("Collected MAX_TASKS nodes"), Box::new(TaskDataCache::default()))
+
+}
+
+#[cfg(not(feature = "std"))]
+fn task_nodes() -> (TaskNodes, TaskCache) {
+
+    (core::array::from_fn(|_| TaskNode::empty()), TaskDataCache::default())
+
+}
+
 /// Stores and manages all tasks and their data
 pub struct TaskManager {
 
     /// the number of active nodes
     pub n_nodes: usize,
     /// list of nodes
-    pub nodes: [TaskNode; MAX_TASKS],
+    pub nodes: TaskNodes,
     /// buffer containing each tasks output data
-    pub data_cache: TaskDataCache,
+    pub data_cache: TaskCache,
+    /// number of packets dropped for a bad checksum
+    pub corrupted: usize,
+    /// number of packets dropped because they don't decode (task, mode or
+    /// header out of range), the sender's limits differ from ours
+    pub rejected: usize,
+    /// sequence number of the next packet from [TaskManager::spin()]
+    pub sequence: u16,
+    /// capabilities of the remote counter part, None until the handshake completes
+    pub remote: Option<Capabilities>,
+    /// why the remote can't run the loaded tasks, the host won't configure it while this is set
+    pub config_error: Option<ConfigError>,
+    /// triggers waiting to run (client) or to be acknowledged (host)
+    pub triggers: TriggerQueue,
+    /// host time of the current tick, clients set this before [TaskManager::spin()].
+    /// Triggers wait while it is None (the client clock is not synced)
+    pub host_time: Option<Micros>,
+    /// the last trigger that ran and the host time of the tick it ran on
+    pub fired: Option<(Trigger, Micros)>,
+    /// a hello request is waiting for a reply
+    hello_pending: bool,
+    /// a trigger is waiting to be acknowledged
+    trigger_ack: Option<Trigger>,
 
 }
 
@@ -303,11 +351,23 @@ impl TaskManager {
     /// no tasks.
     pub fn default() -> TaskManager {
 
+        let (nodes, data_cache) = task_nodes();
+
         TaskManager {
 
             n_nodes: 0,
-            nodes: core::array::from_fn(|_| TaskNode::empty()),
-            data_cache: TaskDataCache::default(),
+            nodes,
+            data_cache,
+            corrupted: 0,
+            rejected: 0,
+            sequence: 0,
+            remote: None,
+            config_error: None,
+            triggers: TriggerQueue::default(),
+            host_time: None,
+            fired: None,
+            hello_pending: false,
+            trigger_ack: None,
         
         }
     }
@@ -342,87 +402,234 @@ impl TaskManager {
     /// to a remote counter part. 
     ///
     /// Identical implementation for clients and hosts. 
-    /// The [PacketType], embedded as as a u8 at 
-    /// [RID_MODE_INDEX], determines how the node
+    /// The [RidPacket] decoded from the buffer determines how the node
     /// should handle the data. Any node receiving a panic
     /// will call [TaskNode::kill()] and deinitialize.
     ///
     /// The [TaskNode] packet collector functions will assert 
     /// the node is in the correct state before handling any data.
+    ///
+    /// Packets that fail the checksum are counted in 
+    /// [TaskManager::corrupted] and dropped, packets that fail
+    /// any other check in [TaskManager::rejected].
+    ///
+    /// [RidPacket::Hello] packets update [TaskManager::remote]. Clients queue
+    /// [RidPacket::Trigger]s and hosts drop the ones that were acknowledged.
     pub fn collect(&mut self, buffer: &RIDReport) -> bool {
 
-        let mut node_select = buffer[RID_TASK_INDEX] as usize;
+        let packet = match RidPacket::decode(buffer) {
+            Ok(packet) => packet,
 
-        match node_select > 0 {
-            true => {
+            Err(DecodeError::Checksum) => {
 
-                node_select -= 1;
+                self.corrupted += 1;
+                return false;
 
-                match PacketType::new(buffer[RID_MODE_INDEX]) {
-                    PacketType::Init => {
+            },
 
-                        self.nodes[node_select].init(&buffer[RTNT_HDR_INDEX..RTNT_DATA_INDEX], &buffer[RTNT_DATA_INDEX..RTNT_DATA_INDEX+MAX_TASK_DATA_BYTES]);
-                        false
+            Err(_) => {
 
-                    },
+                self.rejected += 1;
+                return false;
 
-                    PacketType::Chunk => {
+            },
+        };
 
-                        self.nodes[node_select].collect_chunk(&buffer[RTNT_HDR_INDEX..RTNT_DATA_INDEX], &buffer[RTNT_DATA_INDEX..RTNT_DATA_INDEX+MAX_TASK_DATA_BYTES]);
-                        false
+        match packet {
+            RidPacket::Init { task, stream, rate, driver, n_inputs, n_outputs, inputs } => {
 
-                    },
+                self.nodes[task].init(stream, rate, driver, n_inputs, n_outputs, &inputs);
+                false
 
-                    PacketType::Status => {
+            },
 
-                        self.nodes[node_select].config_cache.collect_status(&buffer[RTNT_DATA_INDEX..RTNT_DATA_INDEX+MAX_TASK_DATA_BYTES]);
-                        self.nodes[node_select].status = match self.nodes[node_select].config_cache.missing_chunks() > 0 { true => TaskStatus::Configuration, false => TaskStatus::Active, };
-                        // println!("Status reply {node_select} {}", self.nodes[node_select].data.missing_chunks());
-                        false
+            RidPacket::Chunk { task, id, chunk, total, data } => {
 
-                    },
+                self.nodes[task].collect_chunk(id, chunk, total, &data);
+                false
 
-                    PacketType::Data => {
+            },
 
-                        self.nodes[node_select].status = TaskStatus::Active;
-                        let bytes = &buffer[RTNT_DATA_INDEX..MAX_TASK_DATA_BYTES];
+            RidPacket::Status { task, missing } => {
 
-                        for i in 0..self.nodes[node_select].n_outputs as usize {
-                            
-                            self.nodes[node_select].data[i] = f32::from_be_bytes([bytes[i*4], bytes[(i*4)+1], bytes[(i*4)+2], bytes[(i*4)+3]]);
+                self.nodes[task].config_cache.collect_status(&missing);
+                self.nodes[task].status = match self.nodes[task].config_cache.missing_chunks() > 0 { true => TaskStatus::Configuration, false => TaskStatus::Active, };
+                false
 
-                        }
-                        self.nodes[node_select].stream > 0
+            },
+
+            RidPacket::Data { task, data } => {
+
+                let n_outputs = (self.nodes[task].n_outputs as usize).min(MAX_TASK_DATA_FLOATS);
+
+                self.nodes[task].status = TaskStatus::Active;
+                self.nodes[task].data[..n_outputs].copy_from_slice(&data[..n_outputs]);
+
+                self.nodes[task].stream > 0
+
+            },
+
+            RidPacket::Kill => {
+
+                for i in 0..self.n_nodes {
+
+                    self.nodes[i].status = TaskStatus::Panic;
+                
+                }
+
+                false
+            },
+
+            RidPacket::Hello { kind, capabilities } => {
+
+                match kind == HELLO_REQUEST {
+                    true => {
+
+                        // a new host counts trigger ids from 0
+                        self.hello_pending = true;
+                        self.triggers.reset();
 
                     },
+                    false => self.config_error = self.check(&capabilities).err(),
+                }
 
-                    PacketType::Kill => {
+                self.remote = Some(capabilities);
 
-                        for i in 0..self.n_nodes {
+                false
 
-                            self.nodes[i].status = TaskStatus::Panic;
-                        
+            },
+
+            RidPacket::Trigger { kind, trigger } => {
+
+                match kind == TRIGGER_REQUEST {
+                    true => {
+
+                        // a full queue doesn't acknowledge, the host will resend
+                        if self.triggers.push(trigger) {
+                            self.trigger_ack = Some(trigger);
                         }
 
-                        false
+                    },
+                    false => {
+
+                        self.triggers.remove(trigger.id);
+
                     },
                 }
 
+                false
+
             },
 
-            false => false,
-        
+            RidPacket::Idle | RidPacket::Ptp => false,
         }
         
-        
+    }
+
+    /// The remote restarted (host only). Forget its [Capabilities] so the
+    /// next [TaskManager::control_spin()] says hello again, and load every
+    /// task from the start. Queued triggers are kept and sent again.
+    pub fn reconnect(&mut self) {
+
+        self.remote = None;
+        self.config_error = None;
+
+        for i in 0..self.n_nodes {
+
+            match self.nodes[i].status {
+                TaskStatus::Panic => {},
+                _ => self.nodes[i].status = TaskStatus::Standby,
+            }
+
+        }
+
+    }
+
+    /// Check that a remote with the given [Capabilities]
+    /// can run every loaded [TaskNode].
+    pub fn check(&self, remote: &Capabilities) -> Result<(), ConfigError> {
+
+        remote.check_layout()?;
+
+        if self.n_nodes > remote.max_tasks as usize {
+            return Err(ConfigError::TooManyTasks(self.n_nodes));
+        }
+
+        for i in 0..self.n_nodes {
+
+            remote.check_node(i, &self.nodes[i])?;
+
+        }
+
+        Ok(())
+
+    }
+
+    /// Number a packet going to the host.
+    ///
+    /// [TaskManager::spin()] numbers its own packets, clients
+    /// should also number any idle packets they send.
+    pub fn stamp_sequence(&mut self, buffer: &mut RIDReport) {
+
+        link::write_sequence(buffer, self.sequence);
+        self.sequence = self.sequence.wrapping_add(1);
+
+    }
+
+    /// Run every trigger that is due at [TaskManager::host_time]
+    pub fn run_triggers(&mut self) {
+
+        let now = match self.host_time {
+            Some(now) => now,
+            None => return,
+        };
+
+        while let Some(trigger) = self.triggers.pop_due(now) {
+
+            for i in 0..MAX_TASKS {
+
+                match trigger.action {
+                    TriggerAction::Start if trigger.includes(i) => self.nodes[i].held = false,
+                    TriggerAction::Stop if trigger.includes(i) => self.nodes[i].held = true,
+                    TriggerAction::Kill if self.nodes[i].config_cache.is_init() => self.nodes[i].status = TaskStatus::Panic,
+                    _ => {},
+                }
+
+            }
+
+            self.fired = Some((trigger, now));
+
+        }
+
+    }
+
+    /// Queue a trigger for the remote (host only).
+    ///
+    /// It is sent by [TaskManager::control_spin()] until the client acknowledges it.
+    /// `tasks` has bit i set for the task at index i. `at` has to be less than half
+    /// an hour from the host time it is sent at (see [crate::rtnt::trigger]). Returns the trigger's id,
+    /// None if [crate::rtnt::trigger::MAX_TRIGGERS] are waiting.
+    pub fn trigger(&mut self, at: Micros, action: TriggerAction, tasks: u32) -> Option<u8> {
+
+        let id = self.triggers.next_id();
+
+        match self.triggers.push(Trigger { id, at, action, tasks }) {
+            true => Some(id),
+            false => None,
+        }
+
     }
 
     /// Produce a packet and try configuring or exectuing each task. This is the output
     /// interface for a host counter part. The packets this function produces shoud be delivered
     /// to a host instance. This function should be paired with [TaskManager::collect()] in an
     /// embedded system.
+    ///
+    /// Triggers that are due run first, held tasks don't run.
     pub fn spin(&mut self) -> Option<RIDReport> {
 
+        self.run_triggers();
+
         for i in 0..MAX_TASKS {
             // the number of nodes includes all tasks
             // with config data in use.
@@ -444,16 +651,16 @@ impl TaskManager {
 
                         TaskStatus::Active => {
                             
-                            if !self.data_cache.status_waiting(i) {
+                            if !self.data_cache.status_waiting(i) && !self.nodes[i].held {
 
                                 // let inputs = self.data_cache.task_input_buffer(&self.nodes[i].inputs);
                                 let mut inputs = [0.0f32; MAX_TASK_DATA_FLOATS];
 
-                                for j in 0..self.nodes[i].n_inputs as usize {
+                                for (j, input) in inputs.iter_mut().enumerate().take(self.nodes[i].n_inputs as usize) {
                                     
                                     let id = self.nodes[i].inputs[j][0] as usize;
                                     let index = self.nodes[i].inputs[j][1] as usize;
-                                    inputs[i] = self.nodes[id].data[index];
+                                    *input = self.nodes[id].data[index];
 
                                 }
 
@@ -467,13 +674,12 @@ impl TaskManager {
 
                                     if self.nodes[i].stream > 0 {
 
-                                        let mut buffer = [0u8; MAX_TASK_DATA_BYTES];
+                                        let n_outputs = (self.nodes[i].n_outputs as usize).min(MAX_TASK_DATA_FLOATS);
+                                        let mut data = [0.0f32; MAX_TASK_DATA_FLOATS];
                                         
-                                        for j in 0..self.nodes[i].n_outputs as usize {
-                                            buffer[(4*j)..(4*j)+4].copy_from_slice(&self.nodes[i].data[j].to_be_bytes());
-                                        }
+                                        data[..n_outputs].copy_from_slice(&self.nodes[i].data[..n_outputs]);
 
-                                        self.data_cache.new_output(i, PacketType::Data, buffer);
+                                        self.data_cache.queue(i, RidPacket::Data { task: i, data });
                                     }
                                 }
 
@@ -487,7 +693,7 @@ impl TaskManager {
 
                             self.nodes[i].configure();
 
-                            self.data_cache.new_output(i, PacketType::Status, self.nodes[i].config_cache.emit_status());
+                            self.data_cache.queue(i, self.nodes[i].config_cache.emit_status(i));
                             self.data_cache.context = i;
 
                         },
@@ -500,14 +706,52 @@ impl TaskManager {
             }
         }
 
-        self.data_cache.publish(self.n_nodes)
+        if self.hello_pending {
+
+            self.hello_pending = false;
+
+            let mut report = Capabilities::local().hello(HELLO_REPLY);
+            self.stamp_sequence(&mut report);
+
+            return Some(report);
+
+        }
+
+        if let Some(trigger) = self.trigger_ack.take() {
+
+            let mut report = RidPacket::Trigger { kind: TRIGGER_ACK, trigger }.encode();
+            self.stamp_sequence(&mut report);
+
+            return Some(report);
+
+        }
+
+        let mut report = self.data_cache.publish(self.n_nodes)?;
+        self.stamp_sequence(&mut report);
+
+        Some(report)
     }
 
     /// Produce configuration packets for loaded tasks. This is the output
     /// interface for a client counter part. The packets this function produces shoud be delivered
     /// to a client instance. This function should be paired with [TaskManager::collect()] on a host machine.
+    ///
+    /// Sends [PacketType::Hello] requests until the client shares its [Capabilities].
+    /// Tasks are not configured while the client can't run them (see [TaskManager::config_error]),
+    /// a panic is still forwarded. Queued triggers go out before configuration.
     pub fn control_spin(&mut self) -> Option<RIDReport> {
 
+        match &self.remote {
+            None => return Some(Capabilities::local().hello(HELLO_REQUEST)),
+            Some(remote) => self.config_error = self.check(remote).err(),
+        }
+
+        if let Some(trigger) = self.triggers.first() {
+            return Some(RidPacket::Trigger { kind: TRIGGER_REQUEST, trigger }.encode());
+        }
+
+        let refused = self.config_error.is_some();
+
         for i in 0..self.n_nodes {
 
             // the number of nodes includes all tasks
@@ -519,7 +763,7 @@ impl TaskManager {
                         self.nodes[i].kill();
                     }
 
-                    self.data_cache.new_output(0, PacketType::Kill, [0u8; MAX_TASK_DATA_BYTES]);
+                    self.data_cache.queue(0, RidPacket::Kill);
                     self.data_cache.context = 0;
 
                     self.n_nodes = 0;
@@ -529,17 +773,19 @@ impl TaskManager {
 
                 TaskStatus::Active => {},
 
+                TaskStatus::Standby | TaskStatus::Configuration if refused => {},
+
                 TaskStatus::Standby => {
 
-                    let (header, data) = self.nodes[i].header();
-                    self.data_cache.new_output_with_header(i, PacketType::Init, header, data);
+                    if let Some(packet) = self.nodes[i].init_packet(i) {
+                        self.data_cache.queue(i, packet);
+                    }
 
                 },
 
                 TaskStatus::Configuration => {
 
-                    let (header, data) = self.nodes[i].config_cache.emit_chunk();
-                    self.data_cache.new_output_with_header(i, PacketType::Chunk, header, data);
+                    self.data_cache.queue(i, self.nodes[i].config_cache.emit_chunk(i));
 
                 },
                 
@@ -0,0 +1,251 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Time Triggered Commands
+//!
+//!   A [Trigger] runs an action on a set of tasks at a host time. The host queues
+//! it with [crate::rtnt::task_manager::TaskManager::trigger()] and resends it until
+//! the client acknowledges it. The client holds it until its estimate of the host
+//! time (see [crate::ptp::clock::ClientClock]) passes the trigger time, then runs
+//! it in [crate::rtnt::task_manager::TaskManager::spin()].
+//!
+//!   Trigger times travel like the PTP stamps, microseconds into the hour
+//! ([Micros::wrapped()]). The client rebuilds host time from those stamps so its
+//! hours don't match the host's, it places a trigger within half an hour of its
+//! estimate ([Trigger::due_in()]). Triggers must be sent less than half an hour
+//! before they are due, [crate::host::session::HostSession::trigger()] checks that.
+//!
+//! To start tasks together, stop them right away and start them at a time:
+//!
+//! ```
+//! use rid::{ptp::Micros, rtnt::{task_manager::TaskManager, trigger::TriggerAction}};
+//!
+//! let mut host = TaskManager::default();
+//! let now = Micros::new(10_000_000);
+//!
+//! host.trigger(now, TriggerAction::Stop, 0b11);
+//! host.trigger(Micros::new(12_500_000), TriggerAction::Start, 0b11);
+//! ```
+
+use crate::ptp::{Micros, USEC_PER_HOUR};
+
+/// Number of triggers a [TriggerQueue] can hold
+pub const MAX_TRIGGERS: usize = 8;
+
+/// Header value (index 2) of a trigger sent by a host
+pub const TRIGGER_REQUEST: u8 = 0;
+/// Header value (index 2) of a client acknowledging a trigger
+pub const TRIGGER_ACK: u8 = 1;
+
+/// What a [Trigger] does to its tasks
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum TriggerAction {
+    /// Run the tasks
+    Start,
+    /// Hold the tasks, they keep their configuration but don't run
+    Stop,
+    /// Kill every task
+    Kill,
+}
+
+impl TriggerAction {
+    /// Create an action from u8, None if the value is not an action
+    pub fn new(id: u8) -> Option<TriggerAction> {
+        match id {
+            0 => Some(TriggerAction::Start),
+            1 => Some(TriggerAction::Stop),
+            2 => Some(TriggerAction::Kill),
+            _ => None,
+        }
+    }
+
+    /// Convert Self to a u8
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            TriggerAction::Start => 0,
+            TriggerAction::Stop => 1,
+            TriggerAction::Kill => 2,
+        }
+    }
+}
+
+/// An action on a set of tasks at a host time
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Trigger {
+    /// Set by the host, the client acknowledges this id
+    pub id: u8,
+    /// Host time to run the action at, only the
+    /// microseconds into the hour are sent
+    pub at: Micros,
+    /// The action
+    pub action: TriggerAction,
+    /// Bit i is set for the task at index i
+    pub tasks: u32,
+}
+
+impl Trigger {
+    /// True if the task at the index is in the trigger's set
+    pub fn includes(&self, task: usize) -> bool {
+
+        task < 32 && (self.tasks >> task) & 1 == 1
+
+    }
+
+    /// Microseconds from a host time to the trigger time, negative once it
+    /// is due. Only the place in the hour counts, the result is within half an hour.
+    ///
+    /// ```
+    /// use rid::ptp::{Micros, USEC_PER_HOUR};
+    /// use rid::rtnt::trigger::{Trigger, TriggerAction};
+    ///
+    /// // the host is in its third hour, the client's estimate in its first
+    /// let trigger = Trigger { id: 0, at: Micros::new(3 * USEC_PER_HOUR + 500), action: TriggerAction::Start, tasks: 1 };
+    ///
+    /// assert_eq!(trigger.due_in(Micros::new(USEC_PER_HOUR - 100)), 600);
+    /// assert_eq!(trigger.due_in(Micros::new(USEC_PER_HOUR + 600)), -100);
+    /// ```
+    pub fn due_in(&self, now: Micros) -> i64 {
+
+        let hour = USEC_PER_HOUR as i64;
+        let delta = (self.at.wrapped() as i64 - now.wrapped() as i64).rem_euclid(hour);
+
+        match delta > hour / 2 {
+            true => delta - hour,
+            false => delta,
+        }
+
+    }
+}
+
+/// Fixed capacity set of [Trigger]s
+pub struct TriggerQueue {
+    triggers: [Option<Trigger>; MAX_TRIGGERS],
+    last_id: Option<u8>,
+}
+
+impl Default for TriggerQueue {
+    /// An empty queue
+    fn default() -> TriggerQueue {
+
+        TriggerQueue {
+            triggers: [None; MAX_TRIGGERS],
+            last_id: None,
+        }
+
+    }
+}
+
+impl TriggerQueue {
+    /// Add a trigger, returns false if the queue is full.
+    ///
+    /// Ids must increase (wrapping), a trigger with an id at or
+    /// before the last one added is a resend and is dropped (returns true).
+    pub fn push(&mut self, trigger: Trigger) -> bool {
+
+        if let Some(last) = self.last_id {
+
+            if (trigger.id.wrapping_sub(last) as i8) <= 0 {
+                return true;
+            }
+
+        }
+
+        match self.triggers.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+
+                *slot = Some(trigger);
+                self.last_id = Some(trigger.id);
+                true
+
+            },
+            None => false,
+        }
+
+    }
+
+    /// Remove and return the earliest trigger due at a host time (see
+    /// [Trigger::due_in()]), its time is moved into the hours of `now`
+    pub fn pop_due(&mut self, now: Micros) -> Option<Trigger> {
+
+        let slot = self.triggers
+            .iter_mut()
+            .filter(|slot| matches!(slot, Some(trigger) if trigger.due_in(now) <= 0))
+            .min_by_key(|slot| slot.map(|trigger| trigger.due_in(now)))?;
+
+        slot.take().map(|trigger| Trigger { at: now + trigger.due_in(now), ..trigger })
+
+    }
+
+    /// The oldest trigger (by id) that is still queued
+    pub fn first(&self) -> Option<Trigger> {
+
+        let last = self.last_id?;
+
+        self.triggers
+            .iter()
+            .flatten()
+            .max_by_key(|trigger| last.wrapping_sub(trigger.id))
+            .copied()
+
+    }
+
+    /// Remove the trigger with the id, returns false if it wasn't queued
+    pub fn remove(&mut self, id: u8) -> bool {
+
+        match self.triggers.iter_mut().find(|slot| matches!(slot, Some(trigger) if trigger.id == id)) {
+            Some(slot) => {
+
+                *slot = None;
+                true
+
+            },
+            None => false,
+        }
+
+    }
+
+    /// The id the next trigger pushed should use
+    pub fn next_id(&self) -> u8 {
+
+        match self.last_id {
+            Some(id) => id.wrapping_add(1),
+            None => 0,
+        }
+
+    }
+
+    /// Number of queued triggers
+    pub fn len(&self) -> usize {
+
+        self.triggers.iter().flatten().count()
+
+    }
+
+    /// True if no triggers are queued
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+
+    }
+
+    /// Drop every trigger and forget the last id,
+    /// the remote restarted its count
+    pub fn reset(&mut self) {
+
+        *self = TriggerQueue::default();
+
+    }
+}
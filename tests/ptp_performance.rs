@@ -29,6 +29,7 @@ use hidapi::{HidApi, HidDevice};
 
 use std::{
     env,
+    path::Path,
     net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
     sync::{
         mpsc,
@@ -41,11 +42,15 @@ use std::{
 
 use more_asserts::assert_le;
 
-use gnuplot::{Caption, Color, Figure};
-
 use rid::{
-    ptp::*,
-    host::layer::RIDLayer,
+    ptp::{*, clock::ClockSource, estimator::{SkewEstimator, SKEW_WINDOW}, filter::DelayFilter, servo::ClockServo, stats::{PtpSnapshot, RunningStats}, sync::SyncState},
+    host::{
+        layer::RIDLayer,
+        report::PtpReport,
+        scheduler::CycleScheduler,
+        sim::{SimClient, SimConfig, VirtualClock},
+        transport::{RidTransport, loopback::LoopbackTransport},
+    },
     RID_PACKET_SIZE,
     RID_TASK_INDEX, RID_MODE_INDEX,
     RID_DEFAULT_VID, RID_DEFAULT_PID,
@@ -60,206 +65,553 @@ pub mod ptp_performance {
 
     use super::*;
 
-    pub fn least_squares_conversion(out_time: &Vec<f32>, in_time: &Vec<f32>) -> (f32, f32) {
+    pub fn demo_rid<T: RidTransport>(layer: &mut RIDLayer<T>) {
+
+        println!("[HID-Control]: Live");
+
+        let duration_us = (TEST_DURATION as f64 * 1_000_000.0) as i64;
+
+        let report = PtpReport::run(layer, Duration::from_secs_f32(TEST_DURATION), TEST_DURATION as usize).expect("Link failed");
+
+        println!("[HID-Control]: shutdown {}", layer.host_elapsed());
+
+        let snapshot = report.snapshot;
+        let offset = snapshot.stats.offset;
+        let flight = snapshot.stats.flight;
+
+        println!("\n[PTP-DEMO]\tC(t) = {:.6} * H(t) + {} (+/- {:.1} us, {:.2} ppm), {:?}, {} slow exchanges rejected",
+            snapshot.skew,
+            snapshot.intercept,
+            snapshot.uncertainty.unwrap_or(f64::NAN),
+            snapshot.skew_std.unwrap_or(f64::NAN) * 1E6,
+            snapshot.sync,
+            snapshot.rejected,
+        );
+
+        println!(
+            "PTP Offset stats: \n\tSamples: {}\n\t(mean, std): ({:.3}, {:.3}) us\n\tFlight time (mean, std, max): ({:.1}, {:.1}, {}) us\n\tHOST elapsed time: {} s [{}, {}]\n\tMCU elapsed time: {} s [{}, {}]",
+            offset.count,
+            offset.mean,
+            offset.std(),
+            flight.mean,
+            flight.std(),
+            flight.max,
+            layer.host_elapsed() as f64 / 1_000_000.0,
+            layer.host_start.as_secs_f64(),
+            layer.host_read.as_secs_f64(),
+            layer.client_elapsed() as f64 / 1_000_000.0,
+            layer.client_start.unwrap_or_default().as_secs_f64(),
+            layer.client_write.as_secs_f64(),
+        );
 
-        let n = in_time.len() as f32;
-        
-        let (sigma, sigma_bar) = in_time.iter().fold((0.0, 0.0), |(sum, sum_sq), &t| ( sum + t, sum_sq + (t*t) ));
-        
-        let gamma = (n * sigma_bar) - (sigma * sigma);
+        let results = env::var("RID_PTP_RESULTS").unwrap_or("target/ptp_results".to_string());
+        let rendered = report.save(Path::new(&results)).expect("Failed to save the PTP report");
 
-        let (m, b) = out_time
-            .iter()
-            .zip(in_time)
-            .fold((0.0, 0.0), |(m, b), (&to, ti)| ( 
-                m + ((n * to * ti) - sigma),
-                b + (to * (sigma_bar - (ti * sigma)))
-            ));
+        println!("[PTP-DEMO]: results in {results} (plots rendered: {rendered})");
 
-        (m / gamma, b / gamma)
+        assert_le!(0.9, report.writes as f64 / (TEST_DURATION as f64 / RID_CYCLE_TIME_S), "Insufficient writes to client");
+        assert_le!(offset.std() / 1_000_000.0, TEST_DURATION as f64 / 175.0, "PTP offset STD was too large");
+        assert_le!(0, layer.client_elapsed(), "MCU elapsed time is invalid");
+        assert_le!(0.98, layer.client_elapsed() as f64 / duration_us as f64, "Time elapsed differs on MCU");
+        assert_le!(0.98, layer.host_elapsed() as f64 / duration_us as f64, "Time elapsed differs on HOST");
     }
 
-    pub fn demo_rid(layer: &mut RIDLayer) {
+    #[test]
+    pub fn hid_spawner() {
+        /*
+            Start an hid layer
+        */
+        let mut layer = RIDLayer::new(RID_DEFAULT_VID, RID_DEFAULT_PID).expect("Failed to open the device");
+
+        demo_rid(&mut layer);
+    }
 
-        println!("[HID-Control]: Live");
+    #[test]
+    pub fn sim_spawner() {
+        /*
+            Start a simulated client with a drifting clock
+        */
+        let (host, client) = LoopbackTransport::pair();
+
+        let config = SimConfig {
+            offset: USEC_PER_SEC * SEC_PER_HOUR as u32 - 2_000_000,
+            skew_ppm: 120.0,
+            jitter_us: 100,
+            ..SimConfig::default()
+        };
+
+        let sim = SimClient::new(client, config).spawn();
+
+        let mut layer = RIDLayer::from_transport(host);
+        layer.read_timeout = Duration::from_micros(500);
+
+        demo_rid(&mut layer);
 
-        let mut local_offset = vec![];
-        
-        let mut packet_flight_time = vec![];
+        let client = sim.stop().expect("Simulated client failed");
+        assert_le!(0.9, client.replies as f64 / (TEST_DURATION as f64 / RID_CYCLE_TIME_S), "Insufficient replies from client");
 
-        let mut host_truth: Vec<f32> = vec![];
-        let mut client_truth: Vec<f32> = vec![];
+        let snapshot = layer.snapshot();
+        let stats = snapshot.stats;
+
+        assert_eq!(stats.replies, stats.offset.count, "Offset statistics missed replies");
+        assert_eq!(stats.replies, stats.flight.count + 1, "Flight statistics missed replies");
+        assert_eq!(stats.flight.count, stats.flight_histogram.count(), "Histogram missed flights");
+        assert_le!(stats.replies, stats.exchanges, "More replies than exchanges");
+        assert_eq!(stats.exchanges, layer.sequence as usize, "Exchanges were not counted");
+        assert_le!(stats.flight.mean, 500.0, "Flights are slow");
+        // the client runs on a real time thread, a loaded machine adds a few 100us
+        assert_le!(stats.host_error.std(), 1_000.0, "Servo conversions are noisy");
+        assert_eq!(snapshot.sync, SyncState::Locked, "Layer is not locked");
+
+        let logged = toml::to_string(&snapshot).expect("Snapshot did not serialize");
+        assert_eq!(toml::from_str::<PtpSnapshot>(&logged).expect("Snapshot did not deserialize"), snapshot, "Snapshot changed in the log");
+    }
+
+    #[test]
+    pub fn ptp_report() {
+        /*
+            Characterize a simulated client and
+            write the results to a new directory
+        */
+        let (host, client) = LoopbackTransport::pair();
+        let sim = SimClient::new(client, SimConfig::default()).spawn();
 
-        let mut client_prediction: Vec<f32> = vec![];
+        let mut layer = RIDLayer::from_transport(host);
+        layer.read_timeout = Duration::from_millis(1);
 
-        let mut host_ptp_error: Vec<f32> = vec![];
-        let mut client_ptp_error: Vec<f32> = vec![];
+        let report = PtpReport::run(&mut layer, Duration::from_millis(300), 5).expect("Link failed");
 
-        let mut write_count = 0.0;
+        sim.stop().expect("Simulated client failed");
 
-        while layer.host_elapsed() / 1_000_000.0 < TEST_DURATION
-        {
+        assert_le!(250, report.writes, "Report missed cycles");
+        assert_le!(report.writes / 5 - 5, report.samples.len(), "Report missed samples");
+        assert!(report.samples.windows(2).all(|s| s[0].host_elapsed < s[1].host_elapsed), "Samples are out of order");
 
-            let flight_time = layer.spin();
-            
-            if flight_time > 0.0 && layer.host_elapsed() > 1_000.0 {
+        let dir = env::temp_dir().join(format!("rid_ptp_report_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
 
-                if write_count as u32 % (100 * TEST_DURATION as u32) == 0 {
+        let rendered = report.save(&dir).expect("Failed to save the report");
 
-                    layer.print_header();
-                
-                }
+        let csv = std::fs::read_to_string(dir.join("samples.csv")).expect("No samples were written");
+        assert_eq!(csv.lines().count(), report.samples.len() + 1, "Samples are missing from the CSV");
+        assert!(csv.starts_with("host_elapsed_us,"), "CSV has no header");
 
-                if write_count as u32 % TEST_DURATION as u32 == 0 {
-                    
-                    local_offset.push(layer.ptp_offset());
+        for name in ["offset", "ptp_offset_err", "flight_time", "linear_conv"] {
+            assert!(dir.join(format!("{name}.gp")).exists(), "No gnuplot script for {name}");
+            assert_eq!(dir.join(format!("{name}.png")).exists(), rendered, "Report misreported the {name} plot");
+        }
 
-                    let (ho_err, cl_err) = layer.print();
+        // a script that can't be written is an error, not a panic
+        std::fs::remove_file(dir.join("offset.gp")).expect("No script to replace");
+        std::fs::create_dir(dir.join("offset.gp")).expect("Failed to block the script");
+        assert!(report.save(&dir).is_err(), "Report saved over a directory");
 
-                    host_ptp_error.push(ho_err);
-                    client_ptp_error.push(cl_err);
+        let _ = std::fs::remove_dir_all(&dir);
 
-                    packet_flight_time.push(flight_time);
+    }
 
-                    client_truth.push(layer.client_elapsed());
-                    host_truth.push(layer.host_elapsed());
+    #[test]
+    pub fn cycle_scheduler() {
+        /*
+            Pace 500 cycles with one overrun, the deadlines
+            after it should not move
+        */
+        let mut scheduler = CycleScheduler::default();
+        let start = Instant::now();
 
-                    client_prediction.push(layer.linear_to_client(TEST_DURATION * 1_000_000.0) / 1_000_000.0);
-                }
+        for i in 0..500 {
 
+            if i == 100 {
+                std::thread::sleep(Duration::from_micros(3_500));
             }
 
-            write_count += 1.0;
+            scheduler.wait();
+
+        }
+
+        let elapsed = start.elapsed().as_micros() as i64;
+
+        println!("Scheduler jitter (mean, std, max): ({:.1}, {:.1}, {:.1}) us, {} missed",
+            scheduler.jitter.mean,
+            scheduler.jitter.std(),
+            scheduler.jitter.max,
+            scheduler.missed,
+        );
+
+        // an oversleep can miss a deadline too
+        assert_le!(1, scheduler.missed, "Overrun was not counted");
+        assert_eq!(scheduler.jitter.count + scheduler.missed, 500, "Waits were not measured");
+        assert_le!(scheduler.jitter.mean, 100.0, "Scheduler wakes late");
+
+        // missed deadlines are skipped, the rest stay on the grid
+        assert_le!(502, scheduler.cycles(), "Missed deadlines were not skipped");
+        assert_le!((elapsed - (scheduler.cycles() as i64 * 1_000)).abs(), 500, "Deadlines drifted");
+
+    }
+
+    #[test]
+    pub fn duration_epochs() {
+        /*
+            Follow a counter through a few hours, every
+            rollover is counted once
+        */
+        let hour = USEC_PER_HOUR as u32;
+        let mut timer = rid::ptp::Duration::new(hour - 3_000);
+
+        // the first stamp can't go before the epoch
+        assert_eq!(timer.unwrap(hour - 1).epoch(), 0, "Unwrapped before the epoch");
+
+        let start = timer;
+        let mut last = timer;
+
+        for i in 1..=4 * 3_600u64 {
+
+            let stamp = ((hour - 3_000) as u64 + (i * 1_000_000)) % USEC_PER_HOUR;
+            let full = timer.advance(stamp as u32);
+
+            assert!(timer > last, "Time went backwards at {i}s");
+            assert_eq!(timer - last, 1_000_000, "Step was not a second at {i}s");
+            assert_eq!(full - rid::ptp::Micros::from(start), i as i64 * 1_000_000, "Full time drifted at {i}s");
+
+            last = timer;
+
+        }
+
+        assert_eq!(timer.epoch(), 4, "Rollovers were not counted");
+        assert_eq!(timer.elapsed_since(start), 4 * USEC_PER_HOUR, "Elapsed time lost an hour");
+        assert_eq!(start.elapsed_since(timer), 0, "Elapsed time was negative");
+
+        // a late stamp from just before the rollover stays in the last hour
+        timer.advance(2_000);
+        let late = timer.unwrap(hour - 4_000);
+        assert_eq!(late.epoch(), 4, "Late stamp moved forward an hour");
+        assert_eq!(timer - late, 6_000, "Late stamp was not before the timer");
+
+        timer.add_micros(hour);
+        assert_eq!(timer.epoch(), 6, "Adding an hour did not roll over");
+
+    }
+
+    #[test]
+    pub fn sim_rollover() {
+        /*
+            Cross the client's hourly rollover, the full
+            times should not jump
+        */
+        let (host, client) = LoopbackTransport::pair();
+
+        let config = SimConfig {
+            offset: (USEC_PER_HOUR - 500_000) as u32,
+            skew_ppm: 0.0,
+            rollover: USEC_PER_HOUR as u32,
+            jitter_us: 0,
+            ..SimConfig::default()
+        };
+
+        let sim = SimClient::new(client, config).spawn();
+
+        let mut layer = RIDLayer::from_transport(host);
+        layer.read_timeout = Duration::from_millis(10);
+
+        let mut offsets = vec![];
+
+        while layer.host_elapsed() < 1_000_000 {
+
+            if layer.spin().is_ok_and(|flight_time| flight_time > 0) {
+                offsets.push(layer.ptp_offset());
+            }
 
             layer.timestep();
 
         }
 
-        println!("[HID-Control]: shutdown {}", layer.host_elapsed());
+        sim.stop().expect("Simulated client failed");
 
-        let ptp_mean =
-            local_offset.iter().sum::<f32>() / (local_offset.len() as f32);
-        let ptp_std = (local_offset
-            .iter()
-            .map(|offset| (offset - ptp_mean) * (offset - ptp_mean))
-            .sum::<f32>()
-            / local_offset.len() as f32)
-            .sqrt();
+        assert_le!(USEC_PER_HOUR, layer.client_write.micros(), "Client did not roll over");
+        assert_le!(layer.client_write.wrapped() as u64, 1_000_000, "Client stamp did not wrap");
+        assert_eq!(layer.client_clock.epoch(), 1, "Client rollover was not counted");
 
-        let host_prediction = host_truth.iter().map(|&x| layer.linear_to_client(x) / 1_000_000.0).collect::<Vec<f32>>();
-        let host_scaled = host_truth.iter().map(|&x| x / 1_000_000.0).collect::<Vec<f32>>();
+        // the host starts 5s before the hour, the client 0.5s
+        for offset in offsets {
+            assert_le!((offset - 4_500_000).abs(), 50_000, "PTP offset jumped at the rollover");
+        }
 
-        println!(
-            "PTP Offset stats: \n\tSamples: {}\n\t(mean, std): ({ptp_mean:.3}, {ptp_std:.3}) s\n\tHOST elapsed time: {} s [{}, {}]\n\tMCU elapsed time: {} s [{}, {}]",
-            local_offset.len(),
-            layer.host_elapsed() / 1_000_000.0,
-            layer.host_start / 1_000_000.0,
-            layer.ptp_stamp[2] as f32 / 1_000_000.0,
-            layer.client_elapsed() / 1_000_000.0,
-            layer.client_start / 1_000_000.0,
-            layer.ptp_stamp[1] as f32 / 1_000_000.0,
-        );
+        let client_elapsed = layer.linear_to_client(layer.host_read - layer.host_start);
+        assert_le!((layer.client_elapsed() - client_elapsed).abs(), 5_000, "Client elapsed time jumped at the rollover");
+    }
 
-        let x = (0..local_offset.len())
-            .map(|x| x as f32 * TEST_DURATION)
-            .collect::<Vec<f32>>();
-
-        let mut fg = Figure::new();
-        let mut fg1 = Figure::new();
-        let mut fg2 = Figure::new();
-        let mut fg3 = Figure::new();
-
-        fg.axes2d()
-            .lines(
-                &x,
-                &local_offset,
-                &[Caption("PTP Offset (microseconds)"), Color("black")],
-            )
-            .lines(
-                &x,
-                &vec![ptp_mean; local_offset.len()],
-                &[Caption("Average"), Color("green")],
-            )
-            .lines(
-                &x,
-                &vec![ptp_mean + ptp_std; local_offset.len()],
-                &[Caption("2 Sigma bound"), Color("red")],
-            )
-            .lines(
-                &x,
-                &vec![ptp_mean - ptp_std; local_offset.len()],
-                &[Color("red")],
-            );
-        
-        fg1.axes2d()
-            .lines(
-                &x,
-                &(host_ptp_error),
-                &[Caption("Cr(t) - Hw(t) + o(t) (microseconds)"), Color("red")],
-            )
-            .lines(
-                &x,
-                &(client_ptp_error),
-                &[Caption("Hw(t) - Cr(t) - o(t)"), Color("blue")],
-            );
-        
+    #[test]
+    pub fn sim_short_rollover() {
+        /*
+            A client counter that wraps every second
+        */
+        let clock = VirtualClock::new(999_000, 0.0, 1_000_000);
 
+        std::thread::sleep(Duration::from_millis(5));
 
-        fg2.axes2d()
-            .lines(
-                &x,
-                &packet_flight_time,
-                &[Caption("flight time (microseconds)"), Color("black")],
-            );
+        assert_le!(clock.micros(), 100_000, "Client clock did not roll over");
+    }
+
+    #[test]
+    pub fn clock_servo() {
+        /*
+            Track a client 3s ahead running 120ppm fast through
+            jittery flights, one flight is stuck for 20ms
+        */
+        let mut servo = ClockServo::default();
+        let mut rng = 0x5EEDu64;
 
-        fg3.axes2d()
-            .lines(
-                &host_scaled,
-                &host_prediction,
-                &[Caption("C(t) = m * H(t) + b"), Color("red")],
-            )
-            .lines(
-                &host_scaled,
-                &client_truth.iter().map(|x| x / 1_000_000.0).collect::<Vec<f32>>(),
-                &[Caption("C(t) (seconds)"), Color("blue")],
-            )
-            .lines(
-                &host_scaled,
-                &client_prediction,
-                &[Caption("C = (m * duration) + b"), Color("green")],
+        let mut errors = vec![];
+
+        for i in 0..5_000u64 {
+
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+
+            let out = 20 + (rng % 100);
+            let back = 20 + ((rng >> 32) % 100);
+            let stuck = match i {
+                2_500 => 20_000,
+                _ => 0,
+            };
+
+            let host_write = Micros::new(i * 1_000);
+            let client_read = host_write + out as i64 + 3_000_000 + (i as i64 * 120 / 1_000);
+            let client_write = client_read + 10;
+            let host_read = host_write + (out + back + 10 + stuck) as i64;
+
+            let accepted = servo.sample(host_write, client_read, client_write, host_read);
+
+            if i == 2_500 {
+                assert!(!accepted, "Stuck flight was not rejected");
+            }
+
+            if i > 1_000 {
+                errors.push((servo.offset(host_write) - (3_000_000 + (i as i64 * 120 / 1_000))).abs());
+            }
+
+        }
+
+        assert_eq!(servo.rejected(), 1, "Servo rejected good samples");
+        assert_le!((servo.ratio() - 1.000_12).abs(), 2E-5, "Frequency ratio did not converge");
+        assert_le!(*errors.iter().max().unwrap(), 30, "Offset estimate wandered");
+        assert_le!(servo.uncertainty(), 60.0, "Uncertainty is larger than the jitter");
+    }
+
+    #[test]
+    pub fn skew_estimator() {
+        /*
+            Fit a client 3s ahead running 120ppm fast through
+            jittery flights, the window fills and slides
+        */
+        let mut estimator = SkewEstimator::default();
+        let mut rng = 0x5EEDu64;
+
+        let truth = |host: Micros| host + 3_000_000 + (host.micros() as i64 * 120 / 1_000_000);
+
+        for i in 0..5_000u64 {
+
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+
+            let out = 20 + (rng % 100);
+            let back = 20 + ((rng >> 32) % 100);
+
+            let host_write = Micros::new(i * 1_000);
+            let client_read = truth(host_write + out as i64);
+            let client_write = client_read + 10;
+            let host_read = host_write + (out + back + 10) as i64;
+
+            estimator.sample(host_write, client_read, client_write, host_read);
+
+        }
+
+        let fit = estimator.fit();
+        let now = Micros::new(5_000_000);
+        let future = Micros::new(6_000_000);
+
+        assert_eq!(estimator.len(), SKEW_WINDOW, "Window did not fill");
+        assert_le!((fit.skew() - 1.000_12).abs(), 3.0 * fit.drift_std, "Skew is outside its bounds");
+        assert_le!(fit.drift_std, 2E-6, "Skew is too uncertain");
+
+        for host in [now, future] {
+
+            let (low, high) = fit.to_client_bounds(host, 3.0);
+
+            assert!(low <= truth(host) && truth(host) <= high, "Conversion is outside its bounds {low:?} {high:?}");
+            assert_le!((fit.to_host(fit.to_client(host)) - host).abs(), 1, "Conversions are not inverses");
+
+        }
+
+        assert_le!(fit.uncertainty(now), fit.uncertainty(future), "Extrapolating did not cost confidence");
+    }
+
+    #[test]
+    pub fn delay_filter() {
+        /*
+            Every 10th exchange is held for a USB frame in
+            one direction, only those should be rejected
+        */
+        let mut filter = DelayFilter::default();
+        let mut rng = 0x5EEDu64;
+
+        let mut held = 0;
+
+        for i in 0..1_000u64 {
+
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+
+            let hold = match i % 10 == 9 {
+                true => 1_000,
+                false => 0,
+            };
+
+            let (out, back) = match rng.is_multiple_of(2) {
+                true => (20 + (rng >> 8) % 20 + hold, 20 + (rng >> 16) % 20),
+                false => (20 + (rng >> 8) % 20, 20 + (rng >> 16) % 20 + hold),
+            };
+
+            let host_write = Micros::new(i * 1_000);
+            let client_read = host_write + (out + 3_000_000) as i64;
+            let client_write = client_read + 10;
+            let host_read = host_write + (out + back + 10) as i64;
+
+            let offset = ptp_offset(host_write.micros() as i64, 
+                client_read.micros() as i64, 
+                client_write.micros() as i64, 
+                host_read.micros() as i64,
             );
 
-        
-        let _ = fg.show();
-        let _ = fg.save_to_png("~/RoPro/rid/doc/ptp_results/offset.png", 800, 500);
-        fg.close();
+            match filter.check(host_write, client_read, client_write, host_read) {
+                true => assert_le!((offset - 3_000_000).abs(), 10, "Asymmetric exchange {i} passed"),
+                false => held += 1,
+            }
 
-        let _ = fg1.show();
-        let _ = fg1.save_to_png("~/RoPro/rid/doc/ptp_results/ptp_offset_err.png", 800, 500);
-        fg1.close();
+        }
 
-        let _ = fg2.show();
-        let _ = fg2.save_to_png("~/RoPro/rid/doc/ptp_results/flight_time.png", 800, 500);
-        fg2.close();
+        assert_eq!(held, 100, "Rejected symmetric exchanges");
+        assert_eq!(filter.rejected(), 100);
+        assert_eq!(filter.accepted(), 900);
+        assert!((40..=50).contains(&filter.min_delay()), "Minimum round trip is off");
+    }
 
-        let _ = fg3.show();
-        let _ = fg3.save_to_png("~/RoPro/rid/doc/ptp_results/linear_conv.png", 800, 500);
-        fg3.close();
+    #[test]
+    pub fn sync_states() {
+        /*
+            Lock onto a simulated client, lose it
+            and fault after half the window is lost
+        */
+        let (host, client) = LoopbackTransport::pair();
+        let sim = SimClient::new(client, SimConfig::default()).spawn();
+
+        let mut layer = RIDLayer::from_transport(host);
+        layer.read_timeout = Duration::from_millis(10);
+
+        assert_eq!(layer.sync_state(), SyncState::Unsynced, "Layer started synced");
+
+        while layer.sync_state() != SyncState::Locked && layer.host_elapsed() < 2_000_000 {
+
+            let _ = layer.spin();
+            layer.timestep();
+
+        }
+
+        println!("Locked after {}us, +/- {:.2}us", layer.host_elapsed(), layer.estimator.fit().uncertainty(layer.host_now()));
+        assert_eq!(layer.sync_state(), SyncState::Locked, "Layer did not lock");
+
+        for _ in 0..200 {
+
+            let _ = layer.spin();
+            layer.timestep();
+            assert_eq!(layer.sync_state(), SyncState::Locked, "Layer lost the lock");
+
+        }
+
+        sim.stop().expect("Simulated client failed");
+
+        let mut states = vec![];
+
+        for _ in 0..40 {
+
+            let _ = layer.spin();
+            states.push(layer.sync_state());
+
+        }
+
+        assert_eq!(&states[..3], [SyncState::Locked; 3], "Layer dropped the lock on a single miss");
+        assert_eq!(states[3], SyncState::Holdover, "Layer did not hold over");
+        assert_eq!(states[39], SyncState::Faulted, "Layer did not fault");
 
-        assert_le!(0.9, write_count / (TEST_DURATION as f64 / RID_CYCLE_TIME_S), "Insufficient writes to client");
-        assert_le!(ptp_std / 1_000_000.0, TEST_DURATION / 175.0, "PTP offset STD was too large");
-        assert_le!(0.0, layer.client_elapsed(), "MCU elapsed time is invalid");
-        assert_le!(0.98, (layer.client_elapsed() / 1_000_000.0) / TEST_DURATION, "Time elapsed differs on MCU");
-        assert_le!(0.98, (layer.host_elapsed() / 1_000_000.0) / TEST_DURATION, "Time elapsed differs on HOST");
     }
 
     #[test]
-    pub fn hid_spawner() {
+    pub fn client_clock() {
         /*
-            Start an hid layer
+            The simulated client estimates host time from the
+            stamps the host echoes, it should agree with the host
         */
-        let mut layer = RIDLayer::new(RID_DEFAULT_VID, RID_DEFAULT_PID);
+        let (host, client) = LoopbackTransport::pair();
 
-        demo_rid(&mut layer);
+        let config = SimConfig {
+            skew_ppm: 120.0,
+            jitter_us: 100,
+            ..SimConfig::default()
+        };
+
+        let sim = SimClient::new(client, config).spawn();
+
+        let mut layer = RIDLayer::from_transport(host);
+        layer.read_timeout = Duration::from_micros(500);
+
+        while layer.host_elapsed() < 2_000_000 {
+
+            let _ = layer.spin();
+            layer.timestep();
+
+        }
+
+        let client = sim.stop().expect("Simulated client failed");
+        let clock = &client.clock;
+
+        assert!(clock.is_synced(), "Client never synced");
+        assert_le!(clock.delay_filter.accepted(), client.replies);
+
+        let host_now = layer.host_now();
+        let client_now = clock.host_now();
+
+        assert_le!((client_now - host_now).abs(), 1_000, "Client and host disagree on the host time");
+        assert_le!((clock.fit().skew() - layer.estimator.fit().skew()).abs(), 1E-5, "Client and host disagree on the skew");
+        assert_le!((layer.estimator.fit().skew() - 1.00012).abs(), 1E-5, "Host measured the wrong skew");
+        assert_le!((clock.to_host(clock.to_client(host_now)) - host_now).abs(), 1, "Conversions are not inverses");
+    }
+
+    #[test]
+    pub fn empty_snapshot() {
+        /*
+            A layer that never heard from its client
+            still logs to formats without infinities
+        */
+        let mut stats = RunningStats::default();
+
+        let logged = serde_json::to_string(&stats).expect("Empty stats did not serialize");
+        assert_eq!(serde_json::from_str::<RunningStats>(&logged).expect("Empty stats did not deserialize"), stats, "Empty stats changed in the log");
+
+        stats.add(-3.0);
+        stats.add(5.0);
+        assert_eq!((stats.min, stats.max), (-3.0, 5.0), "Extremes counted the empty state");
+
+        let (host, _client) = LoopbackTransport::pair();
+        let layer = RIDLayer::from_transport(host);
+        let snapshot = layer.snapshot();
+
+        assert_eq!(snapshot.points, 0, "Fresh layer has a fit");
+        assert_eq!(snapshot.uncertainty, None, "Empty fit has an uncertainty");
+
+        let logged = serde_json::to_string(&snapshot).expect("Empty snapshot did not serialize");
+        assert_eq!(serde_json::from_str::<PtpSnapshot>(&logged).expect("Empty snapshot did not deserialize"), snapshot, "Empty snapshot changed in the log");
     }
 }
@@ -0,0 +1,711 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+//!
+//!
+//! # Verify RID transports
+//! Every transport should deliver whole reports and let
+//! the [RIDLayer] stamp them the same way it does over HID.
+#![allow(unused_imports)]
+
+use std::{
+    io::Write,
+    os::unix::net::UnixStream,
+    sync::mpsc::{channel, Receiver},
+    thread::{sleep, spawn},
+    time::{Duration, Instant},
+};
+
+use rid::{
+    RIDReport, RID_PACKET_SIZE, RID_CYCLE_TIME_US,
+    RID_TASK_INDEX, RID_MODE_INDEX,
+    cobs::{encode, COBS_FRAME_SIZE},
+    link::{read_sequence, write_sequence},
+    ptp::{Micros, TimeStamp, USEC_PER_HOUR, sync::SyncState},
+    rtnt::{
+        TaskStatus,
+        hello::{Capabilities, HELLO_REQUEST},
+        trigger::TriggerAction,
+        constant::RTConstant,
+        task_generator::{TaskExecutable, TaskDriver},
+        task_manager::{TaskNode, TaskManager},
+    },
+    host::{
+        error::RidError,
+        layer::RIDLayer,
+        manager::DeviceManager,
+        runtime::{RidRuntime, RUNTIME_QUEUE_DEPTH},
+        session::HostSession,
+        sim::{SimClient, SimConfig},
+        transport::{RidTransport, loopback::LoopbackTransport, serial::SerialTransport, udp::UdpTransport},
+    },
+};
+
+pub mod rid_transport {
+
+    use super::*;
+
+    #[test]
+    pub fn loopback_echo() {
+
+        let (host, mut client) = LoopbackTransport::pair();
+
+        let mut layer = RIDLayer::from_transport(host);
+        layer.read_timeout = Duration::from_millis(100);
+
+        let mut client_stamp = TimeStamp::new(0, 0, 0, 0);
+        let mut buffer = [0u8; RID_PACKET_SIZE];
+
+        buffer[RID_TASK_INDEX] = 1;
+        buffer[RID_MODE_INDEX] = 2;
+        layer.write(&mut buffer).expect("Host failed to write");
+
+        let mut received: RIDReport = [0u8; RID_PACKET_SIZE];
+        assert_eq!(client.recv(&mut received, Duration::from_millis(100)), Ok(()), "Client did not receive the report");
+        assert_eq!(received, buffer, "Report changed in flight");
+
+        client_stamp.client_read(&received, 10);
+        client_stamp.client_stamp(&mut received, 20);
+        assert_eq!(client.send(&received), Ok(()), "Client failed to reply");
+
+        assert_eq!(layer.read(&mut buffer), Ok(()), "Host did not receive the reply");
+        assert_eq!(layer.ptp_stamp[0], 10, "Host did not save the client read time");
+        assert_eq!(layer.ptp_stamp[1], 20, "Host did not save the client write time");
+        assert_eq!(client_stamp[3], layer.ptp_stamp[3], "Client did not save the host write time");
+
+        assert_eq!(layer.read(&mut buffer), Err(RidError::Timeout), "Host read a report that was never sent");
+
+    }
+
+    /// Load a few streaming constants on a host
+    pub fn constant_tasks(n: usize) -> TaskManager {
+
+        let mut tm_host = TaskManager::default();
+
+        for i in 0..n {
+            let task = TaskExecutable::Constant(RTConstant::new(i as f32 + 0.5));
+            tm_host.init_node(TaskNode::new(1, 100, 0, 1, TaskDriver::Constant, task));
+        }
+
+        tm_host
+
+    }
+
+    /// Run the host half of the RTNT protocol over a [RIDLayer]
+    pub fn spin_layer<T: RidTransport>(n: usize, layer: &mut RIDLayer<T>, tm_host: &mut TaskManager) -> usize {
+
+        let mut replies = 0;
+
+        for _ in 0..n {
+
+            let mut buffer = tm_host.control_spin().unwrap_or([0u8; RID_PACKET_SIZE]);
+
+            layer.write(&mut buffer).expect("Host failed to write");
+
+            if layer.read(&mut buffer).is_ok() {
+
+                tm_host.collect(&buffer);
+                replies += 1;
+
+            }
+
+        }
+
+        replies
+
+    }
+
+    #[test]
+    pub fn udp_task_sync() {
+
+        let client = UdpTransport::listen("127.0.0.1:0").expect("Failed to bind client");
+        let host = UdpTransport::connect("127.0.0.1:0", client.local_addr().expect("Client is not bound")).expect("Failed to bind host");
+
+        let sim = SimClient::new(client, SimConfig::default()).spawn();
+
+        let mut layer = RIDLayer::from_transport(host);
+        layer.read_timeout = Duration::from_millis(100);
+
+        let mut tm_host = constant_tasks(3);
+
+        assert_eq!(spin_layer(20, &mut layer, &mut tm_host), 20, "Client did not answer every report");
+
+        let client = sim.stop().expect("Simulated client failed");
+
+        assert_eq!(tm_host.n_nodes, client.tasks.n_nodes, "number of nodes did not syncronize");
+        for i in 0..tm_host.n_nodes {
+            assert_eq!(tm_host.nodes[i].status, TaskStatus::Active, "TaskStatus {i} is not active");
+            assert_eq!(tm_host.nodes[i].status, client.tasks.nodes[i].status, "TaskStatus {i} did not syncronize");
+            assert_eq!(tm_host.nodes[i].config_cache, client.tasks.nodes[i].config_cache, "TaskConfigs {i} did not syncronize");
+            assert_eq!(tm_host.nodes[i].data[0], i as f32 + 0.5, "TaskData {i} did not stream");
+        }
+
+        assert_eq!(layer.link_stats.received, 20, "Host did not track every reply");
+        assert_eq!(layer.link_stats.lost + layer.link_stats.duplicated + layer.link_stats.out_of_order, 0, "Link dropped or repeated reports");
+
+        assert_ne!(layer.ptp_stamp[1], 0, "Client write time was not stamped");
+        assert_eq!(layer.ptp_stamp[3], client.ptp_stamp[3], "Client did not save the host write time");
+
+    }
+
+    #[test]
+    pub fn link_sequence_stats() {
+
+        let (host, mut client) = LoopbackTransport::pair();
+
+        let mut layer = RIDLayer::from_transport(host);
+        let mut buffer = [0u8; RID_PACKET_SIZE];
+
+        // Host numbers each report it writes
+        for i in 0..3u16 {
+            layer.write(&mut buffer).expect("Host failed to write");
+            assert_eq!(client.recv(&mut buffer, Duration::ZERO), Ok(()), "Client did not receive the report");
+            assert_eq!(read_sequence(&buffer), i, "Host report was not numbered");
+        }
+
+        // Client repeats 1, skips 3 and 5, 3 shows up late, then wraps
+        for sequence in [u16::MAX - 1, u16::MAX, 0, 1, 1, 2, 4, 3, 6] {
+            write_sequence(&mut buffer, sequence);
+            client.send(&buffer).expect("Client failed to send");
+        }
+
+        while layer.read(&mut buffer).is_ok() {}
+
+        assert_eq!(layer.link_stats.received, 9, "Received count is wrong");
+        assert_eq!(layer.link_stats.lost, 1, "Lost count is wrong");
+        assert_eq!(layer.link_stats.duplicated, 1, "Duplicated count is wrong");
+        assert_eq!(layer.link_stats.out_of_order, 1, "Out of order count is wrong");
+
+    }
+
+    #[test]
+    pub fn udp_drops_short_datagrams() {
+
+        let mut client = UdpTransport::listen("127.0.0.1:0").expect("Failed to bind client");
+        let address = client.local_addr().expect("Client is not bound");
+        let host = UdpTransport::connect("127.0.0.1:0", address).expect("Failed to bind host");
+
+        host.socket.send_to(&[1u8; RID_PACKET_SIZE - 1], address).expect("Failed to send datagram");
+
+        let mut buffer = [0u8; RID_PACKET_SIZE];
+        let start = Instant::now();
+        assert_eq!(client.recv(&mut buffer, Duration::from_millis(100)), Err(RidError::Timeout), "Short datagram was accepted");
+        assert!(start.elapsed() >= Duration::from_millis(100), "Short datagram cut the timeout short");
+        assert_eq!(client.peer, None, "Peer learned from a bad datagram");
+
+        // a report behind a bad datagram still arrives
+        host.socket.send_to(&[1u8; RID_PACKET_SIZE + 1], address).expect("Failed to send datagram");
+        host.socket.send_to(&[2u8; RID_PACKET_SIZE], address).expect("Failed to send datagram");
+
+        assert_eq!(client.recv(&mut buffer, Duration::from_millis(100)), Ok(()), "Report behind a bad datagram was lost");
+        assert_eq!(buffer, [2u8; RID_PACKET_SIZE], "Report changed in flight");
+        assert!(client.peer.is_some(), "Peer was not learned from the report");
+
+    }
+
+    #[test]
+    pub fn transport_errors() {
+
+        let mut buffer = [0u8; RID_PACKET_SIZE];
+
+        // a listener has no one to reply to yet
+        let mut listener = UdpTransport::listen("127.0.0.1:0").expect("Failed to bind listener");
+        assert_eq!(listener.send(&buffer), Err(RidError::Disconnected), "Listener sent without a peer");
+
+        // the client goes away
+        let (host, client) = LoopbackTransport::pair();
+        let mut layer = RIDLayer::from_transport(host);
+
+        drop(client);
+
+        assert_eq!(layer.spin(), Err(RidError::Disconnected), "Layer did not see the disconnect");
+        assert_eq!((layer.stats.exchanges, layer.stats.replies), (1, 0), "Failed exchange was not counted");
+
+        // the other end of the stream closes
+        let (host, client) = stream_pair();
+        let mut transport = SerialTransport::new(client);
+
+        drop(host);
+
+        assert_eq!(transport.recv(&mut buffer, Duration::from_millis(10)), Err(RidError::Disconnected), "Closed stream timed out");
+        assert_eq!(transport.send(&buffer), Err(RidError::Disconnected), "Wrote to a closed stream");
+
+    }
+
+    /// A connected pair of byte streams, stand in for a pty pair
+    pub fn stream_pair() -> (UnixStream, UnixStream) {
+
+        let (host, client) = UnixStream::pair().expect("Failed to create stream pair");
+
+        host.set_read_timeout(Some(Duration::from_millis(1))).expect("Failed to set timeout");
+        client.set_read_timeout(Some(Duration::from_millis(1))).expect("Failed to set timeout");
+
+        (host, client)
+
+    }
+
+    #[test]
+    pub fn serial_task_sync() {
+
+        let (host, client) = stream_pair();
+
+        let sim = SimClient::new(SerialTransport::new(client), SimConfig::default()).spawn();
+
+        let mut layer = RIDLayer::from_transport(SerialTransport::new(host));
+        layer.read_timeout = Duration::from_millis(100);
+
+        let mut tm_host = constant_tasks(3);
+
+        assert_eq!(spin_layer(20, &mut layer, &mut tm_host), 20, "Client did not answer every report");
+
+        let client = sim.stop().expect("Simulated client failed");
+
+        assert_eq!(tm_host.n_nodes, client.tasks.n_nodes, "number of nodes did not syncronize");
+        for i in 0..tm_host.n_nodes {
+            assert_eq!(tm_host.nodes[i].status, TaskStatus::Active, "TaskStatus {i} is not active");
+            assert_eq!(tm_host.nodes[i].data[0], i as f32 + 0.5, "TaskData {i} did not stream");
+        }
+
+        assert_eq!(layer.transport.dropped(), 0, "Host dropped frames");
+        assert_eq!(client.transport.dropped(), 0, "Client dropped frames");
+
+    }
+
+    #[test]
+    pub fn serial_partial_frame_recovery() {
+
+        let (mut host, client) = stream_pair();
+        let mut transport = SerialTransport::new(client);
+
+        let mut report = [0u8; RID_PACKET_SIZE];
+        report[RID_TASK_INDEX] = 3;
+        report[RID_MODE_INDEX] = 1;
+        report[RID_PACKET_SIZE - 1] = 0xAA;
+
+        let mut frame = [0u8; COBS_FRAME_SIZE];
+        let length = encode(&report, &mut frame);
+
+        // Half a frame, as if the client joined mid transfer
+        host.write_all(&frame[length / 2..length - 1]).expect("Failed to write");
+        host.write_all(&frame[..length]).expect("Failed to write");
+
+        let mut buffer = [0u8; RID_PACKET_SIZE];
+        assert_eq!(transport.recv(&mut buffer, Duration::from_millis(100)), Ok(()), "Transport did not recover");
+        assert_eq!(buffer, report, "Report changed in flight");
+        assert_eq!(transport.dropped(), 1, "Partial frame was not dropped");
+
+        // Corrupt a byte of a full frame
+        frame[length / 2] ^= 0x40;
+        host.write_all(&frame[..length]).expect("Failed to write");
+        frame[length / 2] ^= 0x40;
+        host.write_all(&frame[..length]).expect("Failed to write");
+
+        assert_eq!(transport.recv(&mut buffer, Duration::from_millis(100)), Ok(()), "Transport did not recover");
+        assert_eq!(buffer, report, "Report changed in flight");
+        assert_eq!(transport.dropped(), 2, "Corrupted frame was not dropped");
+
+        // Corrupt the first code byte, the frame still decodes to a full report
+        frame[1] ^= 0x01;
+        host.write_all(&frame[..length]).expect("Failed to write");
+        frame[1] ^= 0x01;
+        host.write_all(&frame[..length]).expect("Failed to write");
+
+        assert_eq!(transport.recv(&mut buffer, Duration::from_millis(100)), Ok(()), "Transport did not recover");
+        assert_eq!(buffer, report, "Report changed in flight");
+        assert_eq!(transport.dropped(), 3, "Corrupted code byte was not dropped");
+
+        // Lose a byte of a full frame
+        host.write_all(&frame[..length / 2]).expect("Failed to write");
+        host.write_all(&frame[(length / 2) + 1..length]).expect("Failed to write");
+        host.write_all(&frame[..length]).expect("Failed to write");
+
+        assert_eq!(transport.recv(&mut buffer, Duration::from_millis(100)), Ok(()), "Transport did not recover");
+        assert_eq!(buffer, report, "Report changed in flight");
+        assert_eq!(transport.dropped(), 4, "Short frame was not dropped");
+
+        assert_eq!(transport.recv(&mut buffer, Duration::from_millis(10)), Err(RidError::Timeout), "Transport read a report that was never sent");
+
+    }
+
+    #[test]
+    pub fn serial_garbage_timeout() {
+
+        let (mut host, client) = stream_pair();
+        let mut transport = SerialTransport::new(client);
+
+        // nothing reads after the timeout, a full socket must not block the noise
+        host.set_nonblocking(true).expect("Failed to set non-blocking");
+
+        // a port that never stops sending partial frames
+        let noise = spawn(move || {
+
+            let start = Instant::now();
+
+            while start.elapsed() < Duration::from_millis(500) {
+                let _ = host.write_all(&[1, 1, 1, 0]);
+            }
+
+        });
+
+        let mut buffer = [0u8; RID_PACKET_SIZE];
+        let start = Instant::now();
+
+        assert_eq!(transport.recv(&mut buffer, Duration::from_millis(20)), Err(RidError::Timeout), "Transport read a report out of garbage");
+        assert!(start.elapsed() < Duration::from_millis(200), "Garbage held the transport past its timeout");
+        assert!(transport.dropped() > 0, "Garbage frames were not dropped");
+
+        noise.join().expect("Noise thread failed");
+
+    }
+
+    #[test]
+    pub fn multi_device_sync() {
+
+        let mut manager = DeviceManager::default();
+        let mut sims = vec![];
+
+        for (i, offset) in [1_000_000, 2_000_000].into_iter().enumerate() {
+
+            let (host, client) = LoopbackTransport::pair();
+            let config = SimConfig { offset, jitter_us: 0, ..SimConfig::default() };
+
+            sims.push(SimClient::new(client, config).spawn());
+
+            let index = manager.add(&format!("sim-{i}"), host);
+            manager.devices[index].session.layer.read_timeout = Duration::from_millis(100);
+            manager.devices[index].session.tasks = constant_tasks(i + 2);
+
+        }
+
+        for _ in 0..20 {
+            assert_eq!(manager.spin(), 2, "A device did not reply");
+        }
+
+        let offsets: Vec<i64> = manager.devices.iter().map(|device| device.session.layer.ptp_offset()).collect();
+        assert!((offsets[1] - offsets[0]).abs() > 500_000, "Devices share PTP state {offsets:?}");
+
+        for (i, sim) in sims.into_iter().enumerate() {
+
+            let client = sim.stop().expect("Simulated client failed");
+            let device = &manager.device(&format!("sim-{i}")).expect("Device was not found by name").session;
+
+            assert_eq!(device.replies, 20, "Device {i} missed replies");
+            assert_eq!(device.tasks.n_nodes, client.tasks.n_nodes, "number of nodes did not syncronize on device {i}");
+
+            for j in 0..device.tasks.n_nodes {
+                assert_eq!(device.tasks.nodes[j].status, TaskStatus::Active, "TaskStatus {j} is not active on device {i}");
+                assert_eq!(device.tasks.nodes[j].data[0], j as f32 + 0.5, "TaskData {j} did not stream on device {i}");
+            }
+
+        }
+
+        assert!(manager.device("sim-2").is_none(), "Found a device that was never added");
+
+    }
+
+    #[test]
+    pub fn runtime_slow_application() {
+
+        let (host, client) = LoopbackTransport::pair();
+        let sim = SimClient::new(client, SimConfig::default()).spawn();
+
+        let mut layer = RIDLayer::from_transport(host);
+        layer.read_timeout = Duration::from_millis(1);
+
+        let runtime = RidRuntime::spawn(layer);
+        let mut tm_host = constant_tasks(3);
+
+        let start = Instant::now();
+        let mut replies = 0;
+
+        // the application takes 5 cycles per loop
+        while start.elapsed() < Duration::from_millis(500) {
+
+            for reply in runtime.receiver.try_iter() {
+
+                assert!(reply.host_read >= reply.host_write, "Reply was read before it was written");
+                tm_host.collect(&reply.report);
+                replies += 1;
+
+            }
+
+            if let Some(report) = tm_host.control_spin() {
+                runtime.sender.send(report).expect("Runtime stopped");
+            }
+
+            sleep(Duration::from_millis(5));
+
+        }
+
+        let dropped = runtime.dropped();
+        let layer = runtime.stop().expect("Runtime failed");
+        let client = sim.stop().expect("Simulated client failed");
+
+        // the runtime kept the cycle while the application was slow
+        assert!(layer.stats.exchanges >= 400, "Runtime missed cycles, {} exchanges", layer.stats.exchanges);
+        assert!(replies + dropped + RUNTIME_QUEUE_DEPTH >= layer.stats.replies, "Replies were lost in the runtime");
+
+        assert_eq!(tm_host.n_nodes, client.tasks.n_nodes, "number of nodes did not syncronize");
+
+        for i in 0..tm_host.n_nodes {
+            assert_eq!(tm_host.nodes[i].status, TaskStatus::Active, "TaskStatus {i} is not active");
+            assert_eq!(tm_host.nodes[i].data[0], i as f32 + 0.5, "TaskData {i} did not stream");
+        }
+
+    }
+
+    /// Hold two tasks, start them with a trigger and check when they started.
+    /// The host clock starts `hours` after the usual [RIDLayer::host_start]
+    pub fn trigger_start(hours: u64) {
+
+        let mut manager = DeviceManager::default();
+
+        let (host, client) = LoopbackTransport::pair();
+        let sim = SimClient::new(client, SimConfig { jitter_us: 0, ..SimConfig::default() }).spawn();
+
+        let index = manager.add("sim-0", host);
+        let device = &mut manager.devices[index].session;
+        device.layer.read_timeout = Duration::from_millis(100);
+        device.layer.host_start = device.layer.host_start + (hours * USEC_PER_HOUR) as i64;
+        device.tasks = constant_tasks(2);
+
+        // hold the tasks as soon as they load
+        assert!(device.trigger(Micros::ZERO, TriggerAction::Stop, 0b11).is_some(), "Stop was not queued");
+
+        while (0..2).any(|i| device.tasks.nodes[i].status != TaskStatus::Active) {
+
+            assert!(device.spin().is_ok(), "Device did not reply");
+            device.layer.timestep();
+
+        }
+
+        // only time critical commands on a locked link
+        while device.layer.sync_state() != SyncState::Locked {
+
+            assert!(device.spin().is_ok(), "Device did not reply");
+            device.layer.timestep();
+
+        }
+
+        assert!((0..2).all(|i| device.tasks.nodes[i].data[0] == 0.0), "Held tasks streamed data");
+
+        let at = device.layer.host_now() + 300_000;
+        let bound = device.trigger(at, TriggerAction::Start, 0b11).expect("Start was not queued");
+
+        assert!(bound < 2 * RID_CYCLE_TIME_US as i64, "Trigger bound is too loose {bound}");
+
+        let mut started = None;
+
+        while started.is_none() && device.layer.host_now() - at < 100_000 {
+
+            assert!(device.spin().is_ok(), "Device did not reply");
+
+            if device.tasks.nodes[0].data[0] != 0.0 {
+                started = Some(device.layer.host_now());
+            }
+
+            device.layer.timestep();
+
+        }
+
+        let started = started.expect("Tasks did not start");
+
+        // the task runs at 100Hz, data can lag the trigger by its period
+        assert!(started - at >= -bound, "Tasks started {}us early", at - started);
+        assert!(started - at <= bound + 10_000 + 2 * RID_CYCLE_TIME_US as i64, "Tasks started {}us late", started - at);
+
+        let client = sim.stop().expect("Simulated client failed");
+        let (trigger, fired) = client.tasks.fired.expect("Client did not run the trigger");
+
+        // the client counts host hours from its first stamp, only the time into the hour is shared
+        assert_eq!(trigger.at.wrapped(), at.wrapped(), "Client ran the wrong trigger");
+        assert!(fired - trigger.at >= 0 && fired - trigger.at <= bound, "Client ran the trigger at {fired:?}, expected {:?} + {bound}", trigger.at);
+
+    }
+
+    #[test]
+    pub fn timed_start() {
+
+        trigger_start(0);
+
+    }
+
+    #[test]
+    pub fn timed_start_past_rollover() {
+
+        // the host has been up for hours when the client connects
+        trigger_start(3);
+
+    }
+
+    #[test]
+    pub fn host_session() {
+
+        let (host, client) = LoopbackTransport::pair();
+        let sim = SimClient::new(client, SimConfig::default()).spawn();
+
+        let mut session = HostSession::new(host);
+        session.layer.read_timeout = Duration::from_millis(100);
+
+        assert_eq!(session.status().remote, None, "Session shook hands before a tick");
+
+        for i in 0..3 {
+            let task = TaskExecutable::Constant(RTConstant::new(i as f32 + 0.5));
+            assert_eq!(session.load(TaskNode::new(1, 100, 0, 1, TaskDriver::Constant, task)), Some(i), "Task {i} was not loaded");
+        }
+
+        while !session.status().is_active() && session.replies < 100 {
+            assert!(session.tick().is_ok(), "Client did not reply");
+        }
+
+        // let every task stream once
+        for _ in 0..20 {
+            assert!(session.tick().is_ok(), "Client did not reply");
+        }
+
+        let status = session.status();
+
+        assert!(status.connected, "Session is not connected");
+        assert!(status.remote.is_some(), "Session did not shake hands");
+        assert_eq!(status.config_error, None, "Client refused the tasks");
+        assert_eq!(status.tasks, vec![TaskStatus::Active; 3], "Tasks did not load");
+
+        for i in 0..3 {
+            assert_eq!(session.tasks.nodes[i].data[0], i as f32 + 0.5, "TaskData {i} did not stream");
+        }
+
+        session.kill();
+
+        for _ in 0..5 {
+            assert!(session.tick().is_ok(), "Client did not reply");
+        }
+
+        let client = sim.stop().expect("Simulated client failed");
+
+        assert!(session.status().tasks.is_empty(), "Host did not unload the tasks");
+        assert!((0..client.tasks.n_nodes).all(|i| client.tasks.nodes[i].status != TaskStatus::Active), "Client tasks survived the kill");
+
+    }
+
+    /// A [LoopbackTransport] that can be unplugged, the next
+    /// end sent on the socket is plugged in on reconnect
+    pub struct Pluggable {
+        pub link: Option<LoopbackTransport>,
+        pub socket: Receiver<LoopbackTransport>,
+    }
+
+    impl RidTransport for Pluggable {
+
+        fn send(&mut self, buffer: &RIDReport) -> Result<(), RidError> {
+
+            self.link.as_mut().ok_or(RidError::Disconnected)?.send(buffer)
+
+        }
+
+        fn recv(&mut self, buffer: &mut RIDReport, timeout: Duration) -> Result<(), RidError> {
+
+            self.link.as_mut().ok_or(RidError::Disconnected)?.recv(buffer, timeout)
+
+        }
+
+        fn reconnect(&mut self) -> Result<(), RidError> {
+
+            self.link = Some(self.socket.try_recv().map_err(|_| RidError::NotFound)?);
+
+            Ok(())
+
+        }
+    }
+
+    #[test]
+    pub fn hot_plug() {
+
+        let (host, client) = LoopbackTransport::pair();
+        let (plug, socket) = channel();
+
+        let sim = SimClient::new(client, SimConfig { offset: 1_000_000, ..SimConfig::default() }).spawn();
+
+        let mut session = HostSession::new(Pluggable { link: Some(host), socket });
+        session.layer.read_timeout = Duration::from_millis(100);
+        session.layer.reconnect_period = Duration::ZERO;
+        session.tasks = constant_tasks(2);
+
+        for _ in 0..20 {
+            assert!(session.spin().is_ok(), "Device did not reply");
+        }
+
+        assert!((0..2).all(|i| session.tasks.nodes[i].status == TaskStatus::Active), "Tasks did not load");
+
+        let offset = session.layer.ptp_offset();
+
+        // the client resets
+        sim.stop().expect("Simulated client failed");
+
+        assert_eq!(session.spin(), Err(RidError::Disconnected), "Layer did not see the disconnect");
+        assert!(!session.layer.connected, "Layer is still connected");
+        assert_eq!(session.spin(), Err(RidError::NotFound), "Layer reconnected to nothing");
+
+        let (host, client) = LoopbackTransport::pair();
+        let sim = SimClient::new(client, SimConfig { offset: 5_000_000, ..SimConfig::default() }).spawn();
+
+        plug.send(host).expect("Failed to plug the client in");
+
+        for _ in 0..20 {
+            assert!(session.spin().is_ok(), "Device did not reply after reconnecting");
+        }
+
+        let client = sim.stop().expect("Simulated client failed");
+
+        assert_eq!(session.layer.reconnects, 1, "Layer did not count the reconnect");
+        assert_eq!(session.layer.link_stats.lost, 0, "Link statistics were not reset");
+        // the new client clock is 4 seconds ahead of the old one
+        let jump = session.layer.ptp_offset() - offset;
+        assert!((jump - 4_000_000).abs() < 100_000, "PTP did not restart, offset moved {jump}us");
+
+        assert_eq!(client.tasks.n_nodes, 2, "Tasks were not pushed again");
+        for i in 0..2 {
+            assert_eq!(session.tasks.nodes[i].status, TaskStatus::Active, "TaskStatus {i} is not active");
+            assert_eq!(client.tasks.nodes[i].status, TaskStatus::Active, "TaskStatus {i} is not active on the client");
+        }
+
+    }
+
+    #[test]
+    pub fn runtime_resends_unanswered() {
+
+        let (plug, socket) = channel();
+
+        let mut layer = RIDLayer::from_transport(Pluggable { link: None, socket });
+        layer.read_timeout = Duration::from_millis(1);
+        layer.reconnect_period = Duration::ZERO;
+        layer.connected = false;
+
+        let runtime = RidRuntime::spawn(layer);
+
+        // queued while nothing is plugged in
+        runtime.sender.send(Capabilities::local().hello(HELLO_REQUEST)).expect("Runtime stopped");
+        sleep(Duration::from_millis(20));
+
+        let (host, client) = LoopbackTransport::pair();
+        let sim = SimClient::new(client, SimConfig::default()).spawn();
+
+        plug.send(host).expect("Failed to plug the client in");
+        sleep(Duration::from_millis(100));
+
+        let layer = runtime.stop().expect("Runtime failed");
+        let client = sim.stop().expect("Simulated client failed");
+
+        assert!(layer.stats.replies > 0, "Client did not reply after reconnecting");
+        assert!(client.tasks.remote.is_some(), "Queued report was not sent again");
+
+    }
+}
@@ -21,12 +21,18 @@
 use std::fs::read_to_string;
 
 use rid::{
-    RID_PACKET_SIZE, 
+    RID_PACKET_SIZE, RID_CRC_INDEX,
     RID_MODE_INDEX, RID_TASK_INDEX,
+    ptp::{Micros, USEC_PER_HOUR},
     rtnt::{
         *,
         task_generator::{TaskExecutable, TaskDriver}, 
-        task_manager::{TaskNode, TaskManager}
+        constant::RTConstant,
+        switch::RTSwitch,
+        task_manager::{TaskNode, TaskManager},
+        hello::{Capabilities, ConfigError, HELLO_REPLY},
+        packet::{RidPacket, DecodeError},
+        trigger::{Trigger, TriggerAction, TRIGGER_REQUEST, TRIGGER_ACK},
     }
 };
 
@@ -175,7 +181,7 @@ pub mod rtnt_comms {
         let mut tm_host = load_file(toml_data);
 
         println!("===== Load 1 =====");
-        spin_local(10, &mut tm, &mut tm_host);
+        spin_local(11, &mut tm, &mut tm_host);
 
         assert_eq!(tm_host.n_nodes, tm.n_nodes, "number of nodes did not syncronize");
         for i in 0..tm_host.n_nodes {
@@ -202,7 +208,7 @@ pub mod rtnt_comms {
         tm_host = load_file(toml_data);
 
         println!("===== Load 1 =====");
-        spin_local(10, &mut tm, &mut tm_host);
+        spin_local(11, &mut tm, &mut tm_host);
 
         assert_eq!(tm_host.n_nodes, tm.n_nodes, "number of nodes did not syncronize after first reinit");
         for i in 0..tm_host.n_nodes {
@@ -216,7 +222,7 @@ pub mod rtnt_comms {
         println!("===== Load 3 =====");
         tm_host = load_file(toml_data_alt);
 
-        spin_local(10, &mut tm, &mut tm_host);
+        spin_local(11, &mut tm, &mut tm_host);
 
         assert_eq!(tm_host.n_nodes, tm.n_nodes, "number of nodes did not syncronize after second reinit");
         for i in 0..tm_host.n_nodes {
@@ -287,8 +293,257 @@ pub mod rtnt_comms {
         
     }
 
-}
+    #[test]
+    pub fn rtnt_drop_corrupted() {
+
+        let toml_data = "examples/data/penguin/nodes.toml";
+
+        let mut tm = TaskManager::default();
+        let mut tm_host = load_file(toml_data);
+
+        spin_local(10, &mut tm, &mut tm_host);
+
+        // A kill packet that had its mode flipped in flight
+        tm_host.panic_all();
+        let mut host_to_client = tm_host.control_spin().expect("Host did not emit a kill");
+        host_to_client[RID_MODE_INDEX] ^= 0x02;
+
+        assert!(!tm.collect(&host_to_client), "Corrupted packet was collected");
+        assert_eq!(tm.corrupted, 1, "Corrupted packet was not counted");
+
+        for i in 0..tm.n_nodes {
+            assert_eq!(tm.nodes[i].status, TaskStatus::Active, "TaskStatus {i} changed from a corrupted packet");
+        }
+
+        // The same packet with one flipped data bit
+        host_to_client[RID_MODE_INDEX] ^= 0x02;
+        host_to_client[RTNT_DATA_INDEX] ^= 0x01;
+
+        assert!(!tm.collect(&host_to_client), "Corrupted packet was collected");
+        assert_eq!(tm.corrupted, 2, "Corrupted packet was not counted");
+
+        host_to_client[RTNT_DATA_INDEX] ^= 0x01;
+        tm.collect(&host_to_client);
+
+        for i in 0..tm.n_nodes {
+            assert_eq!(tm.nodes[i].status, TaskStatus::Panic, "TaskStatus {i} did not collect the kill");
+        }
+
+    }
+
+    #[test]
+    pub fn rtnt_hello_refuses_unsupported() {
+
+        let toml_data = "examples/data/penguin/nodes.toml";
+
+        let mut tm = TaskManager::default();
+        let mut tm_host = load_file(toml_data);
+
+        // The first cycle is only the handshake
+        spin_local(1, &mut tm, &mut tm_host);
+
+        assert_eq!(tm_host.remote, Some(Capabilities::local()), "Host did not collect the client capabilities");
+        assert_eq!(tm.remote, Some(Capabilities::local()), "Client did not collect the host capabilities");
+        assert_eq!(tm_host.config_error, None, "Host refused a compatible client");
+
+        // A client built without the constant driver
+        let mut tm_host = load_file(toml_data);
+        let constant = (0..tm_host.n_nodes).find(|&i| tm_host.nodes[i].driver == Some(TaskDriver::Constant)).expect("No constant task to refuse");
+        let mut remote = Capabilities::local();
+        remote.n_drivers = 1;
+        remote.drivers[0] = TaskDriver::Switch.as_u8();
+
+        tm_host.collect(&remote.hello(HELLO_REPLY));
+
+        assert_eq!(tm_host.config_error, Some(ConfigError::UnsupportedDriver(constant)), "Host accepted an unsupported driver");
+        assert_eq!(tm_host.control_spin(), None, "Host configured a client that can't run the tasks");
+
+        // A client speaking another protocol version
+        let mut tm_host = load_file(toml_data);
+        let mut remote = Capabilities::local();
+        remote.version += 1;
+
+        tm_host.collect(&remote.hello(HELLO_REPLY));
+
+        assert_eq!(tm_host.config_error, Some(ConfigError::Version(remote.version)), "Host accepted a different protocol");
+        assert_eq!(tm_host.control_spin(), None, "Host configured a client with a different protocol");
+
+        // A panic is still forwarded to a refused client
+        tm_host.panic_all();
+        let kill = tm_host.control_spin().expect("Host did not emit a kill");
+        assert_eq!(PacketType::new(kill[RID_MODE_INDEX]), PacketType::Kill, "Host did not emit a kill");
+
+    }
+
+    #[test]
+    pub fn rtnt_hello_input_limit() {
+
+        let local = Capabilities::local();
+        assert_eq!(local.max_inputs as usize, MAX_TASK_INPUTS.min(MAX_TASK_DATA_FLOATS), "Advertised more inputs than an Init can carry");
+
+        let task = TaskExecutable::Constant(RTConstant::new(1.0));
+        let node = TaskNode::new(0, 100, local.max_inputs + 1, 1, TaskDriver::Constant, task);
+        assert_eq!(local.check_node(0, &node), Err(ConfigError::TooManyInputs(0)), "Handshake accepted too many inputs");
+
+        // A host that skipped the handshake, the client counts the Init it can't read
+        let mut tm = TaskManager::default();
+        let init = RidPacket::Init { task: 0, stream: 0, rate: 100, driver: TaskDriver::Constant, n_inputs: local.max_inputs + 1, n_outputs: 1, inputs: [[0u8; 2]; MAX_TASK_INPUTS] }.encode();
+
+        assert!(!tm.collect(&init), "Init with too many inputs was collected");
+        assert_eq!(tm.rejected, 1, "Rejected packet was not counted");
+        assert_eq!(tm.corrupted, 0, "Rejected packet was counted as corrupted");
+        assert_eq!(tm.nodes[0].status, TaskStatus::Standby, "Node was initialized from a rejected packet");
+
+    }
+
+    #[test]
+    pub fn rtnt_packet_codec() {
+
+        let mut inputs = [[0u8; 2]; MAX_TASK_INPUTS];
+        inputs[0] = [1, 0];
+        inputs[1] = [2, 3];
+
+        let mut missing = [false; MAX_TASK_CONFIG_CHUNKS];
+        missing[1] = true;
+
+        let packets = [
+            RidPacket::Idle,
+            RidPacket::Ptp,
+            RidPacket::Init { task: 3, stream: 1, rate: 250, driver: TaskDriver::Switch, n_inputs: 2, n_outputs: 1, inputs },
+            RidPacket::Chunk { task: 0, id: 7, chunk: MAX_TASK_CONFIG_CHUNKS - 1, total: MAX_TASK_CONFIG_CHUNKS, data: [9u8; MAX_TASK_DATA_BYTES] },
+            RidPacket::Status { task: MAX_TASKS - 1, missing },
+            RidPacket::Data { task: 1, data: core::array::from_fn(|i| i as f32 * 0.5) },
+            RidPacket::Kill,
+            RidPacket::Hello { kind: HELLO_REPLY, capabilities: Capabilities::local() },
+            RidPacket::Trigger { kind: TRIGGER_REQUEST, trigger: Trigger { id: 200, at: Micros::new(USEC_PER_HOUR - 1), action: TriggerAction::Kill, tasks: 1 << 31 } },
+            RidPacket::Trigger { kind: TRIGGER_ACK, trigger: Trigger { id: 0, at: Micros::ZERO, action: TriggerAction::Start, tasks: 0 } },
+        ];
+
+        for packet in packets {
+            assert_eq!(RidPacket::decode(&packet.encode()), Ok(packet), "Packet did not survive encoding");
+        }
+
+        // trigger times only carry the time into the hour
+        let trigger = Trigger { id: 1, at: Micros::new(3 * USEC_PER_HOUR + 12_500_000), action: TriggerAction::Start, tasks: 1 };
+        match RidPacket::decode(&RidPacket::Trigger { kind: TRIGGER_REQUEST, trigger }.encode()) {
+            Ok(RidPacket::Trigger { trigger: decoded, .. }) => assert_eq!(decoded.at, Micros::new(12_500_000), "Trigger time was not wrapped"),
+            other => panic!("Trigger did not decode {other:?}"),
+        }
+
+        let mut report = RidPacket::Kill.encode();
+        report[RTNT_DATA_INDEX] ^= 0x80;
+        assert_eq!(RidPacket::decode(&report), Err(DecodeError::Checksum), "Corrupted packet decoded");
+
+        let mut report = RidPacket::Kill.encode();
+        report[RID_TASK_INDEX] = MAX_TASKS as u8 + 1;
+        assert_eq!(RidPacket::decode(&report), Err(DecodeError::InvalidTask(MAX_TASKS as u8 + 1)), "Packet for an invalid task decoded");
+
+        // inputs the client can't read from
+        for input in [[MAX_TASKS as u8, 0], [0, MAX_TASK_DATA_FLOATS as u8]] {
+
+            let mut bad_inputs = inputs;
+            bad_inputs[1] = input;
+
+            let report = RidPacket::Init { task: 3, stream: 1, rate: 250, driver: TaskDriver::Switch, n_inputs: 2, n_outputs: 1, inputs: bad_inputs }.encode();
+            assert_eq!(RidPacket::decode(&report), Err(DecodeError::InvalidHeader), "Init with input {input:?} decoded");
+
+        }
+
+        let n_inputs = MAX_TASK_INPUTS.min(MAX_TASK_DATA_FLOATS) as u8 + 1;
+        let report = RidPacket::Init { task: 3, stream: 1, rate: 250, driver: TaskDriver::Switch, n_inputs, n_outputs: 1, inputs }.encode();
+        assert_eq!(RidPacket::decode(&report), Err(DecodeError::InvalidHeader), "Init with too many inputs decoded");
+
+        // Every output float reaches the host, not just the first few
+        let mut tm_host = TaskManager::default();
+        tm_host.nodes[0].n_outputs = MAX_TASK_DATA_FLOATS as u8;
+        tm_host.nodes[0].stream = 1;
+
+        let data = core::array::from_fn(|i| i as f32 + 1.0);
+        assert!(tm_host.collect(&RidPacket::Data { task: 0, data }.encode()), "Streamed data was not collected");
+        assert_eq!(tm_host.nodes[0].data, data, "Task data was truncated");
+
+    }
+
+    #[test]
+    pub fn rtnt_task_inputs() {
+
+        // switch(gate, signal) passes the signal while the gate is on
+        let mut tm = TaskManager::default();
+        let mut tm_host = TaskManager::default();
+
+        tm_host.init_node(TaskNode::new(0, 100, 0, 1, TaskDriver::Constant, TaskExecutable::Constant(RTConstant::new(1.0))));
+        tm_host.init_node(TaskNode::new(0, 100, 0, 1, TaskDriver::Constant, TaskExecutable::Constant(RTConstant::new(2.5))));
+
+        let mut switch = TaskNode::new(1, 100, 2, 1, TaskDriver::Switch, TaskExecutable::Switch(RTSwitch::default()));
 
+        let mut inputs = [[0u8; 2]; MAX_TASK_INPUTS];
+        inputs[1] = [1, 0];
+        switch.link(inputs);
 
+        tm_host.init_node(switch);
 
+        spin_local(60, &mut tm, &mut tm_host);
 
+        assert_eq!(tm.nodes[2].status, TaskStatus::Active, "Switch did not configure");
+        assert_eq!(tm.nodes[2].data[0], 2.5, "Switch did not read its inputs");
+        assert_eq!(tm_host.nodes[2].data[0], 2.5, "Switch output did not stream");
+
+    }
+
+    #[test]
+    pub fn rtnt_trigger_queue() {
+
+        let mut tm = TaskManager::default();
+        let mut tm_host = load_file("examples/data/penguin/nodes.toml");
+
+        spin_local(60, &mut tm, &mut tm_host);
+        assert_eq!(tm.n_nodes, tm_host.n_nodes, "Tasks did not configure");
+
+        let tasks = (1 << tm.n_nodes) - 1;
+
+        assert_eq!(tm_host.trigger(Micros::new(1_000), TriggerAction::Stop, tasks), Some(0), "Host did not queue a trigger");
+        assert_eq!(tm_host.trigger(Micros::new(3_000), TriggerAction::Start, tasks), Some(1), "Host did not queue a trigger");
+
+        // the client waits for host time, every trigger is acknowledged
+        spin_local(4, &mut tm, &mut tm_host);
+        assert!(tm_host.triggers.is_empty(), "Triggers were not acknowledged");
+        assert_eq!(tm.triggers.len(), 2, "Client did not queue the triggers");
+        assert!(tm.fired.is_none(), "Trigger ran without a host time");
+
+        // a resend is dropped
+        tm.collect(&RidPacket::Trigger { kind: TRIGGER_REQUEST, trigger: tm.triggers.first().unwrap() }.encode());
+        assert_eq!(tm.triggers.len(), 2, "Client queued a resent trigger");
+
+        tm.host_time = Some(Micros::new(2_000));
+        spin_local(1, &mut tm, &mut tm_host);
+        assert!((0..tm.n_nodes).all(|i| tm.nodes[i].held), "Stop did not hold the tasks");
+        assert_eq!(tm.fired.map(|(trigger, _)| trigger.id), Some(0), "Client did not record the trigger");
+
+        tm.host_time = Some(Micros::new(3_000));
+        spin_local(1, &mut tm, &mut tm_host);
+        assert!((0..tm.n_nodes).all(|i| !tm.nodes[i].held), "Start did not release the tasks");
+        assert_eq!(tm.fired, Some((Trigger { id: 1, at: Micros::new(3_000), action: TriggerAction::Start, tasks }, Micros::new(3_000))), "Trigger ran at the wrong time");
+        assert!(tm.triggers.is_empty(), "Client kept a trigger that ran");
+
+    }
+
+    #[test]
+    pub fn rtnt_packet_layout() {
+
+        // Every field of a report is derived from the report size
+        let mut config = [[0u8; MAX_TASK_DATA_BYTES]; MAX_TASK_CONFIG_CHUNKS];
+        config[MAX_TASK_CONFIG_CHUNKS - 1][MAX_TASK_DATA_BYTES - 1] = 1;
+
+        let packet = RidPacket::Chunk { task: MAX_TASKS - 1, id: 1, chunk: MAX_TASK_CONFIG_CHUNKS - 1, total: MAX_TASK_CONFIG_CHUNKS, data: config[MAX_TASK_CONFIG_CHUNKS - 1] };
+        let report = packet.encode();
+
+        assert_eq!(report[RID_CRC_INDEX - 1], 1, "Task data does not end at the checksum");
+        assert_eq!(RidPacket::decode(&report), Ok(packet), "Last chunk did not survive encoding");
+
+        let capabilities = Capabilities::local();
+        assert_eq!(capabilities.packet_size as usize, RID_PACKET_SIZE, "Hello does not share the report size");
+        assert_eq!(capabilities.data_bytes as usize, MAX_TASK_DATA_BYTES, "Hello does not share the data size");
+
+    }
+
+}